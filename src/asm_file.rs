@@ -1,7 +1,14 @@
+use crate::normalize;
+use crate::symbol;
 use crate::Line;
+use crate::CFG;
+use std::collections::{HashMap, HashSet};
+
+/// Marks where the code of a merged cold part starts in a block.
+const COLD_MARKER: &str = ".cold";
 
 #[derive(Debug)]
-pub struct Block(Line, Vec<Line>);
+pub struct Block(Line, Vec<Line>, Option<u64>, usize);
 
 impl Block {
     pub fn label(&self) -> &str {
@@ -22,15 +29,82 @@ impl Block {
         &self.1
     }
 
+    pub fn start_address(&self) -> Option<u64> {
+        match &self.0 {
+            Line::Label(label) => label.address(),
+            _ => None,
+        }
+    }
+
+    /// The lines of the block itself, without its merged cold parts.
+    fn own_lines(&self) -> &[Line] {
+        let end = self
+            .1
+            .iter()
+            .position(|line| matches!(line, Line::LocalLabel(label) if label == COLD_MARKER))
+            .unwrap_or(self.1.len());
+
+        &self.1[..end]
+    }
+
+    /// The address right after the block: the end of its last instruction
+    /// if the encodings are known, otherwise the address of the next label.
+    /// Merged cold parts are not part of the range. Symbol sizes are not
+    /// used, as the disassembly doesn't show them, so without encodings the
+    /// last block of a section has no end.
+    pub fn end_address(&self) -> Option<u64> {
+        let last_end = self.own_lines().iter().rev().find_map(|line| match line {
+            Line::Instruction(i) if i.size() > 0 => i.address().map(|a| a + i.size() as u64),
+            _ => None,
+        });
+
+        last_end.or(self.2)
+    }
+
+    pub fn contains_address(&self, address: u64) -> bool {
+        match (self.start_address(), self.end_address()) {
+            (Some(start), Some(end)) => start <= address && address < end,
+            _ => false,
+        }
+    }
+
+    /// The size of the block in bytes, from its addresses or, if they are not
+    /// known, from the sizes of its instructions (0 if the disassembly shows
+    /// neither). Merged cold parts are included.
+    pub fn byte_size(&self) -> usize {
+        match (self.start_address(), self.end_address()) {
+            (Some(start), Some(end)) if end >= start => (end - start) as usize + self.3,
+            _ => self
+                .1
+                .iter()
+                .map(|line| match line {
+                    Line::Instruction(i) => i.size(),
+                    _ => 0,
+                })
+                .sum(),
+        }
+    }
+
     fn new(line: Line) -> Self {
         let blocks = Vec::new();
-        Self(line, blocks)
+        Self(line, blocks, None, 0)
+    }
+
+    /// Appends the code of an outlined cold part, after a marker.
+    fn merge_cold_part(&mut self, part: Block) {
+        self.3 += part.byte_size();
+        self.1.push(Line::LocalLabel(COLD_MARKER.to_owned()));
+        self.1.extend(part.1);
     }
 
     fn push(&mut self, line: Line) {
         self.1.push(line);
     }
 
+    pub fn instruction_count(&self) -> usize {
+        self.get_stats().0
+    }
+
     fn get_stats(&self) -> (usize, usize, usize) {
         let instructions = self
             .1
@@ -45,7 +119,7 @@ impl Block {
         let others = self
             .1
             .iter()
-            .filter(|line| matches!(line, Line::Other(_)))
+            .filter(|line| matches!(line, Line::Other(_) | Line::LocalLabel(_)))
             .count();
 
         (instructions, blanks, others)
@@ -53,7 +127,23 @@ impl Block {
 
     fn print_summary(&self) {
         let width = 5;
-        println!("  {:>width$} {}", self.1.len(), self.demangled_label());
+        println!(
+            "  {:>width$} {:>7} {}",
+            self.1.len(),
+            self.byte_size(),
+            CFG.name_policy().apply(self.demangled_label())
+        );
+    }
+
+    pub fn print_address_range(&self) {
+        let hex = |address: Option<u64>| address.map_or("?".to_owned(), |a| format!("{:x}", a));
+        println!(
+            "{} - {} ({} bytes) {}",
+            hex(self.start_address()),
+            hex(self.end_address()),
+            self.byte_size(),
+            self.demangled_label()
+        );
     }
 }
 
@@ -74,16 +164,153 @@ impl Section {
 
     pub fn print_block_summary(&self) {
         println!("{} blocks in section {}:", self.1.len(), self.name());
-        println!("instructions / label name");
+        println!("instructions / bytes / label name");
         for block in &self.1 {
             block.print_summary();
         }
     }
 
+    /// Block count, instruction count and bytes per group of blocks, sorted
+    /// by bytes.
+    fn group_stats<F>(&self, group: F) -> Vec<(String, (usize, usize, usize))>
+    where
+        F: Fn(&str) -> String,
+    {
+        let mut groups: HashMap<String, (usize, usize, usize)> = HashMap::new();
+
+        for block in &self.1 {
+            let stats = groups.entry(group(block.demangled_label())).or_default();
+            stats.0 += 1;
+            stats.1 += block.instruction_count();
+            stats.2 += block.byte_size();
+        }
+
+        let mut groups: Vec<_> = groups.into_iter().collect();
+        groups.sort_by(|a, b| {
+            (b.1 .2, b.1 .1)
+                .cmp(&(a.1 .2, a.1 .1))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        groups
+    }
+
+    /// Prints block count, instruction count and bytes per group of blocks
+    /// sharing the first `depth` segments of their paths.
+    pub fn print_group_summary(&self, depth: usize) {
+        let groups = self.group_stats(|name| symbol::group_name(name, depth));
+
+        println!("{} groups in section {}:", groups.len(), self.name());
+        println!("blocks / instructions / bytes / group");
+        for (name, (blocks, instructions, bytes)) in groups {
+            println!("  {:>6} {:>8} {:>9} {}", blocks, instructions, bytes, name);
+        }
+    }
+
+    /// Prints the generic items with more than one instantiation in the
+    /// section, with the number of copies and their total cost.
+    pub fn print_generics_summary(&self) {
+        let groups: Vec<_> = self
+            .group_stats(symbol::generic_item)
+            .into_iter()
+            .filter(|(_, (copies, _, _))| *copies > 1)
+            .collect();
+
+        println!(
+            "{} generic items with several instantiations in section {}:",
+            groups.len(),
+            self.name()
+        );
+        println!("copies / instructions / bytes / generic item");
+        for (name, (copies, instructions, bytes)) in groups {
+            println!("  {:>6} {:>8} {:>9} {}", copies, instructions, bytes, name);
+        }
+    }
+
+    /// Prints groups of distinct symbols whose bodies are identical once
+    /// addresses are normalized, with the bytes that merging them would save.
+    pub fn print_identical_code_summary(&self) {
+        let mut groups: HashMap<u64, Vec<&Block>> = HashMap::new();
+
+        for block in self.1.iter().filter(|block| block.instruction_count() > 0) {
+            groups
+                .entry(normalize::block_hash(block))
+                .or_default()
+                .push(block);
+        }
+
+        let mut groups: Vec<_> = groups
+            .into_values()
+            .filter(|blocks| blocks.len() > 1)
+            .map(|blocks| {
+                let wasted: usize = blocks[1..].iter().map(|block| block.byte_size()).sum();
+                (wasted, blocks)
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| a.1[0].demangled_label().cmp(b.1[0].demangled_label()))
+        });
+
+        let total: usize = groups.iter().map(|(wasted, _)| wasted).sum();
+        println!(
+            "{} groups of identical blocks in section {} ({} bytes wasted):",
+            groups.len(),
+            self.name(),
+            total
+        );
+        for (wasted, blocks) in groups {
+            println!(
+                "  {} copies, {} instructions, {} bytes wasted:",
+                blocks.len(),
+                blocks[0].instruction_count(),
+                wasted
+            );
+            for block in blocks {
+                println!("    {:>7} {}", block.byte_size(), block.demangled_label());
+            }
+        }
+    }
+
     fn new(line: Line) -> Self {
         Self(line, Vec::new(), Block::new(Line::Blank))
     }
 
+    /// Moves the `.cold` and `.cold.N` parts of functions into the blocks of the functions,
+    /// so that each function is compared as a whole. Cold parts whose parent
+    /// is not in the section are left alone.
+    fn merge_cold_parts(&mut self) {
+        let parent_of =
+            |block: &Block| symbol::cold_parent(block.demangled_label()).map(str::to_owned);
+        let names: HashSet<&str> = self.1.iter().map(|block| block.demangled_label()).collect();
+        let is_merged: Vec<bool> = self
+            .1
+            .iter()
+            .map(|block| parent_of(block).is_some_and(|parent| names.contains(parent.as_str())))
+            .collect();
+
+        let mut parts = Vec::new();
+        let mut blocks = Vec::new();
+        for (block, merged) in std::mem::take(&mut self.1).into_iter().zip(is_merged) {
+            if merged {
+                parts.push(block);
+            } else {
+                blocks.push(block);
+            }
+        }
+
+        let index: HashMap<String, usize> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| (block.demangled_label().to_owned(), i))
+            .collect();
+        for part in parts {
+            let parent = parent_of(&part).expect("bug: cold part without parent");
+            blocks[index[&parent]].merge_cold_part(part);
+        }
+
+        self.1 = blocks;
+    }
+
     fn push(&mut self, line: Line) {
         if let Line::Label(_) = &line {
             self.new_block(line);
@@ -93,9 +320,15 @@ impl Section {
     }
 
     fn new_block(&mut self, line: Line) {
-        self.1.push(Block::new(line));
+        let block = Block::new(line);
+
+        if let Some(last) = self.1.last_mut() {
+            last.2 = block.start_address();
+        }
+        self.1.push(block);
     }
 
+    #[allow(clippy::redundant_guards)]
     fn push_to_last_block(&mut self, line: Line) {
         match self.1.len() {
             l if l == 0 => self.2.push(line),
@@ -117,19 +350,30 @@ impl Section {
 }
 
 #[derive(Debug)]
-pub struct AsmFile(Vec<Section>, Section);
+pub struct AsmFile(Vec<Section>, Section, Option<usize>);
 
 impl AsmFile {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let sections = Vec::new();
-        Self(sections, Section::new(Line::Blank))
+        Self(sections, Section::new(Line::Blank), None)
     }
 
     pub fn push(&mut self, line: Line) {
-        if let Line::SectionHeader(_) = &line {
-            self.new_section(line);
+        if let Line::SectionHeader(header) = &line {
+            // sections can be switched back to, as in assembly sources
+            match self.0.iter().position(|s| s.name() == header.name()) {
+                Some(index) => self.2 = Some(index),
+                None => self.new_section(line),
+            }
         } else {
-            self.push_to_last_section(line);
+            self.push_to_current_section(line);
+        }
+    }
+
+    pub fn merge_cold_parts(&mut self) {
+        for section in &mut self.0 {
+            section.merge_cold_parts();
         }
     }
 
@@ -143,14 +387,26 @@ impl AsmFile {
             .find(|section| section.name() == name)
     }
 
+    /// Finds the block whose address range contains the address.
+    pub fn block_at(&self, address: u64) -> Option<(&Section, &Block)> {
+        self.0.iter().find_map(|section| {
+            section
+                .1
+                .iter()
+                .find(|block| block.contains_address(address))
+                .map(|block| (section, block))
+        })
+    }
+
     fn new_section(&mut self, line: Line) {
         self.0.push(Section::new(line));
+        self.2 = Some(self.0.len() - 1);
     }
 
-    fn push_to_last_section(&mut self, line: Line) {
-        match self.0.len() {
-            l if l == 0 => self.1.push(line),
-            l => self.0[l - 1].push(line),
+    fn push_to_current_section(&mut self, line: Line) {
+        match self.2 {
+            None => self.1.push(line),
+            Some(index) => self.0[index].push(line),
         }
     }
 
@@ -193,3 +449,153 @@ impl AsmFile {
         println!("other: {}", others);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::Syntax;
+
+    const OBJDUMP: &str = "
+Disassembly of section .text:
+
+0000000000001000 <foo>:
+    1000:\t55                   \tpush   rbp
+    1001:\t48 89 e5             \tmov    rbp,rsp
+    1004:\tc3                   \tret
+    1005:\tcc                   \tint3
+
+0000000000001010 <bar>:
+    1010:\te8 eb ff ff ff       \tcall   1000 <foo>
+    1015:\tc3                   \tret
+
+Disassembly of section .init:
+
+0000000000002000 <_init>:
+    2000:\tc3                   \tret
+";
+
+    // the same code without the encodings
+    const OBJDUMP_NO_BYTES: &str = "
+Disassembly of section .text:
+
+0000000000001000 <foo>:
+    1000:\tpush   rbp
+    1001:\tmov    rbp,rsp
+    1004:\tret
+
+0000000000001010 <bar>:
+    1010:\tcall   1000 <foo>
+    1015:\tret
+";
+
+    // a function with two cold parts, and a cold part without its function
+    const OBJDUMP_COLD: &str = "
+Disassembly of section .text:
+
+0000000000001000 <foo>:
+    1000:\t55                   \tpush   rbp
+    1001:\t48 89 e5             \tmov    rbp,rsp
+    1004:\tc3                   \tret
+
+0000000000001010 <bar>:
+    1010:\tc3                   \tret
+
+0000000000002000 <foo.cold>:
+    2000:\t0f 0b                \tud2
+
+0000000000002002 <foo.cold.1>:
+    2002:\te8 f9 ef ff ff       \tcall   1000 <foo>
+
+0000000000002007 <baz.cold>:
+    2007:\tc3                   \tret
+";
+
+    fn asm_file(text: &str) -> AsmFile {
+        let mut asm = AsmFile::new();
+        for line in text.lines() {
+            asm.push(Line::from_str(line, Syntax::Intel));
+        }
+        asm
+    }
+
+    #[test]
+    fn test_addresses() {
+        let asm = asm_file(OBJDUMP);
+        let blocks = asm.get_section(".text").unwrap().blocks();
+
+        assert_eq!(blocks[0].start_address(), Some(0x1000));
+        assert_eq!(blocks[0].end_address(), Some(0x1006));
+        assert_eq!(blocks[0].byte_size(), 6);
+        assert_eq!(blocks[1].start_address(), Some(0x1010));
+        assert_eq!(blocks[1].end_address(), Some(0x1016));
+        assert_eq!(blocks[1].byte_size(), 6);
+
+        assert!(blocks[0].contains_address(0x1000));
+        assert!(blocks[0].contains_address(0x1005));
+        assert!(!blocks[0].contains_address(0x1006));
+        assert!(!blocks[0].contains_address(0xfff));
+    }
+
+    #[test]
+    fn test_merge_cold_parts() {
+        let mut asm = asm_file(OBJDUMP_COLD);
+        asm.merge_cold_parts();
+        let blocks = asm.get_section(".text").unwrap().blocks();
+        let labels: Vec<&str> = blocks.iter().map(Block::label).collect();
+
+        assert_eq!(labels, ["foo", "bar", "baz.cold"]);
+
+        // the parts follow the code of the function, in order
+        let ops: Vec<&str> = blocks[0]
+            .lines()
+            .iter()
+            .filter_map(|line| match line {
+                Line::Instruction(i) => Some(i.op()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ops, ["push", "mov", "ret", "ud2", "call"]);
+        assert_eq!(blocks[0].byte_size(), 5 + 2 + 5);
+        assert_eq!(blocks[0].end_address(), Some(0x1005));
+        assert!(!blocks[0].contains_address(0x2000));
+
+        // no `baz` to merge into
+        assert_eq!(blocks[2].instruction_count(), 1);
+        assert_eq!(blocks[2].byte_size(), 1);
+    }
+
+    #[test]
+    fn test_addresses_without_bytes() {
+        let asm = asm_file(OBJDUMP_NO_BYTES);
+        let blocks = asm.get_section(".text").unwrap().blocks();
+
+        // up to the next label
+        assert_eq!(blocks[0].end_address(), Some(0x1010));
+        assert_eq!(blocks[0].byte_size(), 16);
+        assert!(blocks[0].contains_address(0x100f));
+
+        // the last block has nothing to end it
+        assert_eq!(blocks[1].end_address(), None);
+        assert_eq!(blocks[1].byte_size(), 0);
+        assert!(!blocks[1].contains_address(0x1010));
+    }
+
+    #[test]
+    fn test_block_at() {
+        let asm = asm_file(OBJDUMP);
+        let name = |address| {
+            asm.block_at(address)
+                .map(|(section, block)| (section.name(), block.label()))
+        };
+
+        assert_eq!(name(0x1000), Some((".text", "foo")));
+        assert_eq!(name(0x1004), Some((".text", "foo")));
+        // between the padding of `foo` and `bar`
+        assert_eq!(name(0x1008), None);
+        // the last blocks of their sections
+        assert_eq!(name(0x1015), Some((".text", "bar")));
+        assert_eq!(name(0x2000), Some((".init", "_init")));
+        assert_eq!(name(0x1016), None);
+        assert_eq!(name(0x2001), None);
+    }
+}
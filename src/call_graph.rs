@@ -0,0 +1,830 @@
+use crate::asm_file::{Block, Section};
+use crate::diff::{self, Alignment};
+use crate::line::{Instruction, OperandKind};
+use crate::symbol;
+use crate::AsmFile;
+use crate::Line;
+use crate::CFG;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+
+// `1234 <foo+0x10>` in objdump
+static RE_OBJDUMP_TARGET: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[0-9a-f]+ <(.+)>$").expect("bug: wrong regex"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CallKind {
+    Call,
+    /// A jump to another block, usually a tail call.
+    Jump,
+}
+
+impl CallKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallKind::Call => "call",
+            CallKind::Jump => "jump",
+        }
+    }
+
+    fn of(instruction: &Instruction) -> Option<Self> {
+        match instruction.mnemonic().as_ref() {
+            "call" | "invoke" => Some(CallKind::Call),
+            "jmp" | "return_call" => Some(CallKind::Jump),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: CallKind,
+}
+
+/// The calls between the blocks of a file. Nodes are all the blocks of all
+/// sections, numbered in order; edges come from `call` and `jmp` instructions
+/// whose targets are other blocks.
+pub struct CallGraph<'a> {
+    nodes: Vec<(&'a Section, &'a Block)>,
+    edges: BTreeSet<Edge>,
+}
+
+impl<'a> CallGraph<'a> {
+    pub fn new(asm: &'a AsmFile) -> Self {
+        let nodes: Vec<(&Section, &Block)> = asm
+            .sections()
+            .iter()
+            .flat_map(|section| section.blocks().iter().map(move |block| (section, block)))
+            .collect();
+
+        let mut by_address: Vec<(u64, usize)> = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(node, (_, block))| block.start_address().map(|start| (start, node)))
+            .collect();
+        by_address.sort();
+
+        let mut by_name: HashMap<&str, usize> = HashMap::new();
+        for (node, (_, block)) in nodes.iter().enumerate() {
+            by_name.entry(block.demangled_label()).or_insert(node);
+        }
+
+        let resolve = |operand: &str| {
+            resolve_address(&nodes, &by_address, operand)
+                .or_else(|| target_name(operand).and_then(|name| resolve_name(&by_name, name)))
+        };
+
+        let mut edges = BTreeSet::new();
+        for (from, (_, block)) in nodes.iter().enumerate() {
+            let calls = block.lines().iter().filter_map(|line| match line {
+                Line::Instruction(instruction) => {
+                    CallKind::of(instruction).map(|kind| (instruction, kind))
+                }
+                _ => None,
+            });
+
+            for (instruction, kind) in calls {
+                let operands = instruction.operands();
+                match operands.first().and_then(|operand| resolve(operand)) {
+                    Some(to) if to != from => {
+                        edges.insert(Edge { from, to, kind });
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Self { nodes, edges }
+    }
+
+    /// The node of the first block of a section; the blocks of a section are
+    /// numbered in order from there.
+    pub fn section_start(&self, section: &str) -> Option<usize> {
+        self.nodes.iter().position(|(s, _)| s.name() == section)
+    }
+
+    pub fn name(&self, node: usize) -> &'a str {
+        self.nodes[node].1.demangled_label()
+    }
+
+    /// Finds the node of a symbol, by its demangled name with or without the
+    /// hash.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|(_, block)| {
+            block.demangled_label() == name || symbol::strip_hash(block.demangled_label()) == name
+        })
+    }
+
+    pub fn callers(&self, node: usize) -> Vec<&Edge> {
+        self.edges.iter().filter(|edge| edge.to == node).collect()
+    }
+
+    pub fn callees(&self, node: usize) -> Vec<&Edge> {
+        self.edges.iter().filter(|edge| edge.from == node).collect()
+    }
+
+    fn connected_nodes(&self) -> BTreeSet<usize> {
+        self.edges
+            .iter()
+            .flat_map(|edge| [edge.from, edge.to])
+            .collect()
+    }
+
+    pub fn print_text(&self) {
+        let mut from = None;
+
+        for edge in &self.edges {
+            if from != Some(edge.from) {
+                println!("{}", CFG.name_policy().apply(self.name(edge.from)));
+                from = Some(edge.from);
+            }
+            println!(
+                "  {} {}",
+                edge.kind.name(),
+                CFG.name_policy().apply(self.name(edge.to))
+            );
+        }
+    }
+
+    pub fn print_dot(&self) {
+        println!("digraph calls {{");
+        println!("  node [shape=box];");
+        for node in self.connected_nodes() {
+            println!(
+                "  n{} [label=\"{}\"];",
+                node,
+                dot_escape(&CFG.name_policy().apply(self.name(node)))
+            );
+        }
+        for edge in &self.edges {
+            match edge.kind {
+                CallKind::Call => println!("  n{} -> n{};", edge.from, edge.to),
+                CallKind::Jump => println!("  n{} -> n{} [style=dashed];", edge.from, edge.to),
+            }
+        }
+        println!("}}");
+    }
+
+    pub fn print_json(&self) {
+        let nodes: Vec<String> = self
+            .connected_nodes()
+            .into_iter()
+            .map(|node| {
+                let (section, block) = self.nodes[node];
+                format!(
+                    "    {{\"id\": {}, \"name\": {}, \"section\": {}, \"bytes\": {}}}",
+                    node,
+                    json_string(block.demangled_label()),
+                    json_string(section.name()),
+                    block.byte_size()
+                )
+            })
+            .collect();
+        let edges: Vec<String> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                format!(
+                    "    {{\"from\": {}, \"to\": {}, \"kind\": \"{}\"}}",
+                    edge.from,
+                    edge.to,
+                    edge.kind.name()
+                )
+            })
+            .collect();
+
+        println!("{{");
+        println!("  \"nodes\": [\n{}\n  ],", nodes.join(",\n"));
+        println!("  \"edges\": [\n{}\n  ]", edges.join(",\n"));
+        println!("}}");
+    }
+
+    /// Prints who calls the node and whom it calls.
+    pub fn print_neighbours(&self, node: usize) {
+        let callers = self.callers(node);
+        let callees = self.callees(node);
+
+        println!("{}", self.name(node));
+        println!("{} callers:", callers.len());
+        for edge in callers {
+            println!(
+                "  {} {}",
+                edge.kind.name(),
+                CFG.name_policy().apply(self.name(edge.from))
+            );
+        }
+        println!("{} callees:", callees.len());
+        for edge in callees {
+            println!(
+                "  {} {}",
+                edge.kind.name(),
+                CFG.name_policy().apply(self.name(edge.to))
+            );
+        }
+    }
+}
+
+/// The alignment of the blocks of a section, as nodes of the call graphs.
+fn aligned_nodes(
+    left: &CallGraph,
+    right: &CallGraph,
+    section: &str,
+    alignment: &[(Option<usize>, Option<usize>)],
+) -> Alignment {
+    let start1 = left.section_start(section).unwrap_or_default();
+    let start2 = right.section_start(section).unwrap_or_default();
+
+    alignment
+        .iter()
+        .map(|(l, r)| (l.map(|l| start1 + l), r.map(|r| start2 + r)))
+        .collect()
+}
+
+/// The callees added to and removed from each pair of aligned blocks of a
+/// section whose calls changed, by the node on the right. Callees on the
+/// left are named after the blocks they are aligned with, so that renamed
+/// functions do not show up as changes.
+fn call_changes<'a>(
+    left: &CallGraph<'a>,
+    right: &CallGraph<'a>,
+    section: &str,
+    alignment: &[(Option<usize>, Option<usize>)],
+) -> Vec<(usize, Vec<&'a str>, Vec<&'a str>)> {
+    let aligned: HashMap<usize, usize> = aligned_nodes(left, right, section, alignment)
+        .into_iter()
+        .filter_map(|pair| match pair {
+            (Some(l), Some(r)) => Some((l, r)),
+            _ => None,
+        })
+        .collect();
+
+    aligned
+        .iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|(&l, &r)| {
+            let callees1: BTreeSet<&str> = left
+                .callees(l)
+                .iter()
+                .map(|edge| match aligned.get(&edge.to) {
+                    Some(&to) => right.name(to),
+                    None => left.name(edge.to),
+                })
+                .collect();
+            let callees2: BTreeSet<&str> = right
+                .callees(r)
+                .iter()
+                .map(|edge| right.name(edge.to))
+                .collect();
+
+            (callees1 != callees2).then(|| {
+                (
+                    r,
+                    callees2.difference(&callees1).copied().collect(),
+                    callees1.difference(&callees2).copied().collect(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Prints the calls that appeared or disappeared in each pair of aligned
+/// blocks of a section.
+pub fn print_call_diff(
+    left: &CallGraph,
+    right: &CallGraph,
+    section: &str,
+    alignment: &[(Option<usize>, Option<usize>)],
+) {
+    let changes = call_changes(left, right, section, alignment);
+    let (mut added, mut removed) = (0, 0);
+
+    for (r, callees_added, callees_removed) in &changes {
+        println!("{}", CFG.name_policy().apply(right.name(*r)));
+        for callee in callees_added {
+            diff::print_colored(
+                diff::GREEN,
+                format_args!("  + {}", CFG.name_policy().apply(callee)),
+            );
+        }
+        for callee in callees_removed {
+            diff::print_colored(
+                diff::RED,
+                format_args!("  - {}", CFG.name_policy().apply(callee)),
+            );
+        }
+        added += callees_added.len();
+        removed += callees_removed.len();
+    }
+
+    println!();
+    println!(
+        "{} calls added, {} calls removed in {} functions",
+        added,
+        removed,
+        changes.len()
+    );
+}
+
+/// A function that is only on one side, with the callers that changed size
+/// in the opposite direction: each caller as a node of the right graph, with
+/// its change in bytes.
+type InliningChange = (usize, Vec<(usize, i64)>);
+
+/// Functions whose inlining likely changed: nodes of the left graph gone from
+/// the right whose callers grew (likely inlined), and nodes of the right graph
+/// new since the left whose callers shrank (likely out-of-line copies of
+/// formerly inlined code).
+fn inlining_changes(
+    left: &CallGraph,
+    right: &CallGraph,
+    section: &str,
+    alignment: &[(Option<usize>, Option<usize>)],
+) -> (Vec<InliningChange>, Vec<InliningChange>) {
+    let aligned = aligned_nodes(left, right, section, alignment);
+    let to_right: HashMap<usize, usize> = aligned
+        .iter()
+        .filter_map(|pair| match pair {
+            (Some(l), Some(r)) => Some((*l, *r)),
+            _ => None,
+        })
+        .collect();
+    let to_left: HashMap<usize, usize> = to_right.iter().map(|(l, r)| (*r, *l)).collect();
+    let size = |graph: &CallGraph, node: usize| graph.nodes[node].1.byte_size() as i64;
+
+    let inlined = aligned
+        .iter()
+        .filter(|pair| pair.1.is_none())
+        .filter_map(|(l, _)| {
+            let l = l.expect("bug: empty alignment pair");
+            let grown: Vec<_> = left
+                .callers(l)
+                .iter()
+                .filter_map(|edge| {
+                    let r = *to_right.get(&edge.from)?;
+                    let delta = size(right, r) - size(left, edge.from);
+                    (delta > 0).then_some((r, delta))
+                })
+                .collect();
+            (!grown.is_empty()).then_some((l, grown))
+        })
+        .collect();
+
+    let uninlined = aligned
+        .iter()
+        .filter(|pair| pair.0.is_none())
+        .filter_map(|(_, r)| {
+            let r = r.expect("bug: empty alignment pair");
+            let shrunk: Vec<_> = right
+                .callers(r)
+                .iter()
+                .filter_map(|edge| {
+                    let l = *to_left.get(&edge.from)?;
+                    let delta = size(right, edge.from) - size(left, l);
+                    (delta < 0).then_some((edge.from, delta))
+                })
+                .collect();
+            (!shrunk.is_empty()).then_some((r, shrunk))
+        })
+        .collect();
+
+    (inlined, uninlined)
+}
+
+/// Prints the functions whose inlining likely changed, as found by
+/// `inlining_changes`.
+pub fn print_inlining_changes(
+    left: &CallGraph,
+    right: &CallGraph,
+    section: &str,
+    alignment: &[(Option<usize>, Option<usize>)],
+) {
+    let (inlined, uninlined) = inlining_changes(left, right, section, alignment);
+    let size = |graph: &CallGraph, node: usize| graph.nodes[node].1.byte_size();
+
+    println!("likely inlined (removed, callers grew):");
+    for (l, grown) in inlined {
+        println!(
+            "  {} ({} bytes)",
+            CFG.name_policy().apply(left.name(l)),
+            size(left, l)
+        );
+        for (r, delta) in grown {
+            println!(
+                "    into {} ({:+} bytes)",
+                CFG.name_policy().apply(right.name(r)),
+                delta
+            );
+        }
+    }
+
+    println!();
+    println!("likely un-inlined (added, callers shrank):");
+    for (r, shrunk) in uninlined {
+        println!(
+            "  {} ({} bytes)",
+            CFG.name_policy().apply(right.name(r)),
+            size(right, r)
+        );
+        for (caller, delta) in shrunk {
+            println!(
+                "    from {} ({:+} bytes)",
+                CFG.name_policy().apply(right.name(caller)),
+                delta
+            );
+        }
+    }
+}
+
+/// Resolves a target given as an address (`1234 <foo+0x10>` in objdump).
+fn resolve_address(
+    nodes: &[(&Section, &Block)],
+    by_address: &[(u64, usize)],
+    operand: &str,
+) -> Option<usize> {
+    if !operand.contains('<') {
+        return None;
+    }
+
+    let address = u64::from_str_radix(operand.split_whitespace().next()?, 16).ok()?;
+    let index = by_address.partition_point(|(start, _)| *start <= address);
+    let (_, node) = by_address[index.checked_sub(1)?];
+
+    nodes[node].1.contains_address(address).then_some(node)
+}
+
+fn resolve_name(by_name: &HashMap<&str, usize>, name: &str) -> Option<usize> {
+    by_name.get(name).copied().or_else(|| {
+        // `foo@PLT` in assembly sources
+        let (name, _) = name.rsplit_once('@')?;
+        by_name.get(name).copied()
+    })
+}
+
+/// The symbol a call or jump operand refers to: `<foo+0x10>` after an
+/// address in objdump, `@foo(...)` in LLVM IR, or the operand itself in
+/// assembly sources. Indirect calls and jumps, through a register or memory,
+/// have none.
+fn target_name(operand: &str) -> Option<&str> {
+    if let Some(caps) = RE_OBJDUMP_TARGET.captures(operand) {
+        let name = caps.get(1).map_or("", |m| m.as_str());
+        return Some(match name.rsplit_once("+0x") {
+            Some((name, offset)) if offset.chars().all(|c| c.is_ascii_hexdigit()) => name,
+            _ => name,
+        });
+    }
+
+    let at = if operand.starts_with('@') {
+        Some(0)
+    } else {
+        operand.find(" @").map(|space| space + 1)
+    };
+    if let Some(at) = at {
+        return Some(ir_function_name(&operand[at + 1..]));
+    }
+
+    let name = operand.trim();
+    (!name.is_empty() && !name.starts_with('*') && OperandKind::of(name) == OperandKind::Other)
+        .then_some(name)
+}
+
+/// The name of the function at the start of an LLVM IR call target
+/// (`"a::b"(...)` or `a::Vec<T>::push(...)`), without its arguments.
+fn ir_function_name(target: &str) -> &str {
+    if let Some(quoted) = target.strip_prefix('"') {
+        return &quoted[..quoted.find('"').unwrap_or(quoted.len())];
+    }
+
+    // demangled names have parentheses of their own inside generic arguments
+    let mut depth = 0_i32;
+    let mut previous = ' ';
+    for (i, c) in target.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' if previous != '-' => depth -= 1,
+            '(' if depth == 0 => return &target[..i],
+            _ => (),
+        }
+        previous = c;
+    }
+
+    target
+}
+
+pub fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 2);
+
+    result.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_asm::read_asm_from_str;
+
+    const OBJDUMP: &str = "
+Disassembly of section .text:
+
+0000000000001000 <leaf>:
+    1000:\t31 c0                \txor    eax,eax
+    1002:\tc3                   \tret
+
+0000000000001010 <caller>:
+    1010:\te8 eb ff ff ff       \tcall   1000 <leaf>
+    1015:\t74 02                \tje     1019 <caller+0x9>
+    1017:\teb 00                \tjmp    1019 <caller+0x9>
+    1019:\te9 02 00 00 00       \tjmp    1020 <tail>
+
+0000000000001020 <tail>:
+    1020:\te8 db ff ff ff       \tcall   1000 <leaf>
+    1025:\tff d0                \tcall   rax
+    1027:\tff 25 d3 2f 00 00    \tjmp    QWORD PTR [rip+0x2fd3]
+";
+
+    fn edges<'a>(graph: &CallGraph<'a>) -> Vec<(&'a str, &'a str, &'static str)> {
+        graph
+            .edges
+            .iter()
+            .map(|edge| (graph.name(edge.from), graph.name(edge.to), edge.kind.name()))
+            .collect()
+    }
+
+    #[test]
+    fn test_edges() {
+        let asm = read_asm_from_str(OBJDUMP);
+        let graph = CallGraph::new(&asm);
+
+        // jumps within a function and indirect calls are not edges
+        assert_eq!(
+            edges(&graph),
+            [
+                ("caller", "leaf", "call"),
+                ("caller", "tail", "jump"),
+                ("tail", "leaf", "call"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edges_by_name() {
+        let asm = read_asm_from_str(
+            "\t.text
+leaf:
+\tret
+caller:
+\tcall\tleaf@PLT
+\tjmp\tleaf
+",
+        );
+        let graph = CallGraph::new(&asm);
+
+        assert_eq!(
+            edges(&graph),
+            [("caller", "leaf", "call"), ("caller", "leaf", "jump")]
+        );
+    }
+
+    #[test]
+    fn test_callers_callees() {
+        let asm = read_asm_from_str(OBJDUMP);
+        let graph = CallGraph::new(&asm);
+        let names = |edges: Vec<&Edge>, callers: bool| -> Vec<&str> {
+            edges
+                .iter()
+                .map(|edge| graph.name(if callers { edge.from } else { edge.to }))
+                .collect()
+        };
+        let leaf = graph.find("leaf").unwrap();
+        let caller = graph.find("caller").unwrap();
+        let tail = graph.find("tail").unwrap();
+
+        assert_eq!(names(graph.callers(leaf), true), ["caller", "tail"]);
+        assert!(graph.callees(leaf).is_empty());
+        assert!(graph.callers(caller).is_empty());
+        assert_eq!(names(graph.callees(caller), false), ["leaf", "tail"]);
+        assert_eq!(names(graph.callers(tail), true), ["caller"]);
+        assert_eq!(names(graph.callees(tail), false), ["leaf"]);
+        assert_eq!(graph.section_start(".text"), Some(0));
+        assert_eq!(graph.find("missing"), None);
+    }
+
+    // `helper` is inlined into `caller`, which grows, and part of `shrinker`
+    // moves out into `outlined`
+    const LEFT: &str = "
+Disassembly of section .init:
+
+0000000000000500 <_init>:
+     500:\tc3                   \tret
+
+Disassembly of section .text:
+
+0000000000001000 <helper>:
+    1000:\t31 c0                \txor    eax,eax
+    1002:\tc3                   \tret
+
+0000000000001010 <caller>:
+    1010:\te8 eb ff ff ff       \tcall   1000 <helper>
+    1015:\tc3                   \tret
+
+0000000000001020 <shrinker>:
+    1020:\t01 c0                \tadd    eax,eax
+    1022:\t01 c0                \tadd    eax,eax
+    1024:\t01 c0                \tadd    eax,eax
+    1026:\tc3                   \tret
+";
+
+    const RIGHT: &str = "
+Disassembly of section .text:
+
+0000000000002000 <caller>:
+    2000:\t31 c0                \txor    eax,eax
+    2002:\t01 c0                \tadd    eax,eax
+    2004:\t01 c0                \tadd    eax,eax
+    2006:\t01 c0                \tadd    eax,eax
+    2008:\tc3                   \tret
+
+0000000000002010 <shrinker>:
+    2010:\te8 0b 00 00 00       \tcall   2020 <outlined>
+    2015:\tc3                   \tret
+
+0000000000002020 <outlined>:
+    2020:\t01 c0                \tadd    eax,eax
+    2022:\t01 c0                \tadd    eax,eax
+    2024:\t01 c0                \tadd    eax,eax
+    2026:\tc3                   \tret
+";
+
+    // the blocks of `.text`, aligned by name
+    const ALIGNMENT: &[(Option<usize>, Option<usize>)] = &[
+        (Some(0), None),
+        (Some(1), Some(0)),
+        (Some(2), Some(1)),
+        (None, Some(2)),
+    ];
+
+    #[test]
+    fn test_aligned_nodes() {
+        let (asm1, asm2) = (read_asm_from_str(LEFT), read_asm_from_str(RIGHT));
+        let (left, right) = (CallGraph::new(&asm1), CallGraph::new(&asm2));
+
+        assert_eq!(
+            aligned_nodes(&left, &right, ".text", ALIGNMENT),
+            [
+                (Some(1), None),
+                (Some(2), Some(0)),
+                (Some(3), Some(1)),
+                (None, Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_changes() {
+        let (asm1, asm2) = (read_asm_from_str(LEFT), read_asm_from_str(RIGHT));
+        let (left, right) = (CallGraph::new(&asm1), CallGraph::new(&asm2));
+
+        assert_eq!(
+            call_changes(&left, &right, ".text", ALIGNMENT),
+            [(0, vec![], vec!["helper"]), (1, vec!["outlined"], vec![])]
+        );
+        assert!(call_changes(&left, &left, ".text", &[(Some(1), Some(1))]).is_empty());
+    }
+
+    #[test]
+    fn test_inlining_changes() {
+        let (asm1, asm2) = (read_asm_from_str(LEFT), read_asm_from_str(RIGHT));
+        let (left, right) = (CallGraph::new(&asm1), CallGraph::new(&asm2));
+        let (inlined, uninlined) = inlining_changes(&left, &right, ".text", ALIGNMENT);
+
+        // `helper` (node 1 on the left) went into `caller` (node 0 on the
+        // right), which grew from 6 to 9 bytes
+        assert_eq!(inlined, [(1, vec![(0, 3)])]);
+        // `outlined` came out of `shrinker`, which shrank from 7 to 6 bytes
+        assert_eq!(uninlined, [(2, vec![(1, -1)])]);
+    }
+
+    #[test]
+    fn test_target_name() {
+        assert_eq!(target_name("1234 <a::foo+0x10>"), Some("a::foo"));
+        assert_eq!(target_name("10a0 <memcpy@plt>"), Some("memcpy@plt"));
+        assert_eq!(target_name("void @foo(ptr %v)"), Some("foo"));
+        assert_eq!(target_name("i32 @\"a::b\"(i32 1)"), Some("a::b"));
+        assert_eq!(target_name("memcpy@PLT"), Some("memcpy@PLT"));
+        assert_eq!(target_name("QWORD PTR [rip+0x2ef7]"), None);
+        assert_eq!(target_name("rax"), None);
+        assert_eq!(target_name("r11"), None);
+        assert_eq!(target_name("*%rax"), None);
+        assert_eq!(target_name("%rdx"), None);
+        assert_eq!(target_name("*0x8(%rax)"), None);
+        assert_eq!(target_name("0x8(%rax)"), None);
+
+        // generic names in assembly sources and LLVM IR
+        assert_eq!(
+            target_name("alloc::raw_vec::RawVec<T,A>::grow_one"),
+            Some("alloc::raw_vec::RawVec<T,A>::grow_one")
+        );
+        assert_eq!(
+            target_name("<T as core::clone::Clone>::clone"),
+            Some("<T as core::clone::Clone>::clone")
+        );
+        assert_eq!(
+            target_name("void @alloc::raw_vec::RawVec<T,A>::grow_one(ptr %v)"),
+            Some("alloc::raw_vec::RawVec<T,A>::grow_one")
+        );
+        assert_eq!(
+            target_name("void @core::ptr::drop_in_place<(u8,u16)>(ptr %v)"),
+            Some("core::ptr::drop_in_place<(u8,u16)>")
+        );
+        assert_eq!(target_name("<4 x i32> @f(<4 x i32> %v)"), Some("f"));
+        assert_eq!(
+            target_name("14700 <alloc::raw_vec::RawVec<T,A>::grow_one+0x10>"),
+            Some("alloc::raw_vec::RawVec<T,A>::grow_one")
+        );
+    }
+
+    // `a::parse` calls `alloc::raw_vec::RawVec<T,A>::grow_one`
+    const GROW_ONE: &str = "_ZN5alloc7raw_vec19RawVec$LT$T$C$A$GT$8grow_one17h1212d1987e1f53dbE";
+    const PARSE: &str = "_ZN1a5parse17h93caca7f32fbdaeeE";
+
+    fn callees_of_parse(text: &str) -> Vec<String> {
+        let asm = read_asm_from_str(text);
+        let graph = CallGraph::new(&asm);
+        let parse = graph.find("a::parse").unwrap();
+
+        graph
+            .callees(parse)
+            .iter()
+            .map(|edge| graph.name(edge.to).to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_generic_callee_in_gas() {
+        let text = format!(
+            "\t.text
+{grow_one}:
+\tretq
+{parse}:
+\tcallq\t{grow_one}
+\tretq
+",
+            grow_one = GROW_ONE,
+            parse = PARSE
+        );
+
+        assert_eq!(
+            callees_of_parse(&text),
+            ["alloc::raw_vec::RawVec<T,A>::grow_one::h1212d1987e1f53db"]
+        );
+    }
+
+    #[test]
+    fn test_generic_callee_in_llvm_ir() {
+        let text = format!(
+            "
+define void @{grow_one}(ptr %v) {{
+start:
+  ret void
+}}
+
+define i32 @{parse}(ptr %v) {{
+start:
+  call void @{grow_one}(ptr %v)
+  ret i32 0
+}}
+",
+            grow_one = GROW_ONE,
+            parse = PARSE
+        );
+
+        assert_eq!(
+            callees_of_parse(&text),
+            ["alloc::raw_vec::RawVec<T,A>::grow_one::h1212d1987e1f53db"]
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+}
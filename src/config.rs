@@ -1,7 +1,15 @@
 use clap::Parser as _;
 use once_cell::sync::Lazy;
 
-pub static CFG: Lazy<Config> = Lazy::new(|| Config::parse());
+use crate::line::Syntax;
+use crate::symbol::NamePolicy;
+
+#[cfg(not(test))]
+pub static CFG: Lazy<Config> = Lazy::new(Config::parse);
+
+// tests run with the defaults, not with the arguments of the test binary
+#[cfg(test)]
+pub static CFG: Lazy<Config> = Lazy::new(|| Config::parse_from(["asmdiff", "-", "--mode", "diff"]));
 
 // TODO: separate commands for what is now the mode
 
@@ -18,6 +26,14 @@ pub struct Config {
     pub level: Option<String>,
     #[clap(long)]
     pub no_hash: bool,
+    #[clap(long, value_parser = ["att", "intel", "none"], default_value = "intel")]
+    pub syntax: String,
+    #[clap(long, value_parser = ["auto", "objdump", "gas", "llvm-ir"], default_value = "auto")]
+    pub input_format: String,
+    #[clap(long)]
+    pub strip_debug: bool,
+    #[clap(long)]
+    pub merge_cold: bool,
     #[clap(long, value_parser)]
     pub section: Option<String>,
     #[clap(long, value_parser)]
@@ -26,16 +42,72 @@ pub struct Config {
     pub right_ix: Option<usize>,
     #[clap(long, value_parser)]
     pub summary_type: Option<String>,
+    #[clap(long, value_parser)]
+    pub address: Option<String>,
+    #[clap(long, value_parser)]
+    pub depth: Option<usize>,
+    #[clap(long, value_parser = ["text", "dot", "json"], default_value = "text")]
+    pub graph_format: String,
+    #[clap(long, value_parser)]
+    pub symbol: Option<String>,
     #[clap(long)]
     pub no_color: bool,
+    #[clap(long)]
+    pub show_bytes: bool,
+    #[clap(long)]
+    pub show_cost: bool,
+    #[clap(long, value_parser = ["skylake", "icelake", "zen3", "zen4"], default_value = "skylake")]
+    pub uarch: String,
     #[clap(long, value_parser)]
     pub side_by_side_width: Option<usize>,
     #[clap(long)]
+    pub elide_generics: bool,
+    #[clap(long, value_parser)]
+    pub keep_segments: Option<usize>,
+    #[clap(long)]
+    pub abbreviate_paths: bool,
+    #[clap(long)]
+    pub truncate_names: bool,
+    #[clap(long)]
     pub only_diff: bool,
     #[clap(long)]
+    pub only_loops: bool,
+    #[clap(long, value_parser = ["delta", "percent", "size", "name"], default_value = "delta")]
+    pub sort: String,
+    #[clap(long, value_parser)]
+    pub threshold: Option<String>,
+    #[clap(long)]
+    pub no_content_match: bool,
+    #[clap(long, value_parser = ["exact", "fuzzy"], default_value = "exact")]
+    pub name_match: String,
+    #[clap(long, value_parser)]
+    pub rename_map: Option<String>,
+    #[clap(long, value_parser, default_value_t = 80.0)]
+    pub min_similarity: f64,
+    #[clap(long)]
     pub only_adds: bool,
     #[clap(long)]
     pub only_dels: bool,
     #[clap(long)]
     pub only_dels_and_adds: bool,
 }
+
+impl Config {
+    pub fn syntax(&self) -> Syntax {
+        Syntax::from_name(&self.syntax).expect("unknown --syntax")
+    }
+
+    /// The width of each side in side-by-side diffs.
+    pub fn column_width(&self) -> usize {
+        self.side_by_side_width.unwrap_or(90)
+    }
+
+    pub fn name_policy(&self) -> NamePolicy {
+        NamePolicy {
+            elide_generics: self.elide_generics,
+            keep_segments: self.keep_segments,
+            abbreviate_paths: self.abbreviate_paths,
+            max_width: self.truncate_names.then(|| self.column_width()),
+        }
+    }
+}
@@ -0,0 +1,818 @@
+use crate::asm_file::{Block, Section};
+use crate::call_graph;
+use crate::cost;
+use crate::diff::Alignment;
+use crate::line::Instruction;
+use crate::matching::{self, Match, MatchKind};
+use crate::Line;
+use crate::CFG;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+static RE_IR_LABEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"label %("(?:[^"\\]|\\.)*"|[-a-zA-Z$._0-9]+)"#).expect("bug: wrong regex")
+});
+
+/// Times the body of a loop is assumed to run per entry, when estimating
+/// costs.
+const LOOP_ITERATIONS: f64 = 10.0;
+
+/// Loop nesting beyond which basic blocks are not assumed to run more often.
+const MAX_LOOP_DEPTH: usize = 3;
+
+/// What an instruction does to the flow of control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    /// Goes on with the next instruction.
+    Next,
+    /// Goes to its targets only (`jmp`, LLVM's `br`).
+    Jump,
+    /// Goes to its target or the next instruction (`jne`).
+    Branch,
+    /// Leaves the function (`ret`, `ud2`).
+    Stop,
+}
+
+impl Flow {
+    fn of(instruction: &Instruction) -> Self {
+        let mnemonic = instruction.mnemonic();
+
+        match mnemonic.as_ref() {
+            "ret" | "iret" | "ud2" | "hlt" | "unreachable" | "resume" => Flow::Stop,
+            "jmp" => Flow::Jump,
+            "br" | "switch" | "indirectbr" | "invoke" | "callbr"
+                if RE_IR_LABEL.is_match(instruction.content()) =>
+            {
+                Flow::Jump
+            }
+            "loop" | "loope" | "loopne" => Flow::Branch,
+            op if op.starts_with('j') => Flow::Branch,
+            _ => Flow::Next,
+        }
+    }
+}
+
+/// A straight sequence of lines of a block, entered only at the top and left
+/// only at the bottom.
+#[derive(Debug)]
+pub struct BasicBlock {
+    /// Range of line indices in the block.
+    pub start: usize,
+    pub end: usize,
+    pub successors: Vec<usize>,
+}
+
+/// A natural loop: a header basic block and the basic blocks of its body
+/// (including the header).
+#[derive(Debug)]
+pub struct Loop {
+    pub header: usize,
+    pub body: BTreeSet<usize>,
+}
+
+/// The control-flow graph of a function (a `Block`), with its lines split
+/// into basic blocks at branch targets and after branches and returns.
+pub struct ControlFlowGraph<'a> {
+    block: &'a Block,
+    basic_blocks: Vec<BasicBlock>,
+    /// (branch line, target line) for each branch inside the function.
+    jumps: Vec<(usize, usize)>,
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    pub fn new(block: &'a Block) -> Self {
+        let lines = block.lines();
+        let targets = BranchTargets::new(lines);
+
+        let mut jumps = Vec::new();
+        let mut leaders = vec![0];
+        for (index, line) in lines.iter().enumerate() {
+            if let Line::Instruction(instruction) = line {
+                let flow = Flow::of(instruction);
+
+                if flow != Flow::Next {
+                    leaders.push(index + 1);
+                    for target in targets.resolve(index, instruction) {
+                        leaders.push(target);
+                        jumps.push((index, target));
+                    }
+                }
+            }
+        }
+        leaders.sort_unstable();
+        leaders.dedup();
+
+        let starts = Self::merge_empty_ranges(lines, &leaders);
+        let bb_of_line = |line: usize| starts.partition_point(|start| *start <= line) - 1;
+
+        let basic_blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(bb, &start)| {
+                let end = starts.get(bb + 1).copied().unwrap_or(lines.len());
+                let last = lines[start..end]
+                    .iter()
+                    .rposition(|line| matches!(line, Line::Instruction(_)));
+                let flow = match last.map(|i| &lines[start + i]) {
+                    Some(Line::Instruction(instruction)) => Flow::of(instruction),
+                    _ => Flow::Next,
+                };
+
+                let mut successors: Vec<usize> = jumps
+                    .iter()
+                    .filter(|(from, _)| (start..end).contains(from))
+                    .map(|(_, to)| bb_of_line(*to))
+                    .collect();
+                if matches!(flow, Flow::Next | Flow::Branch) && end < lines.len() {
+                    successors.push(bb + 1);
+                }
+                successors.sort_unstable();
+                successors.dedup();
+
+                BasicBlock {
+                    start,
+                    end,
+                    successors,
+                }
+            })
+            .collect();
+
+        Self {
+            block,
+            basic_blocks,
+            jumps,
+        }
+    }
+
+    /// Turns sorted leaders into the starts of basic blocks that contain
+    /// instructions: lines without instructions (labels, trailing blanks) join
+    /// the basic block after them, or the last one.
+    fn merge_empty_ranges(lines: &[Line], leaders: &[usize]) -> Vec<usize> {
+        let leaders: Vec<usize> = leaders
+            .iter()
+            .copied()
+            .filter(|leader| *leader < lines.len())
+            .collect();
+        let has_instruction = |start: usize, end: usize| {
+            lines[start..end]
+                .iter()
+                .any(|line| matches!(line, Line::Instruction(_)))
+        };
+
+        let mut starts = Vec::new();
+        let mut i = 0;
+        while i < leaders.len() {
+            let start = leaders[i];
+            let mut next = i + 1;
+            while next < leaders.len() && !has_instruction(start, leaders[next]) {
+                next += 1;
+            }
+
+            if next == leaders.len() && !has_instruction(start, lines.len()) && !starts.is_empty() {
+                break;
+            }
+            starts.push(start);
+            i = next;
+        }
+
+        if starts.is_empty() {
+            starts.push(0);
+        }
+        starts
+    }
+
+    pub fn basic_blocks(&self) -> &[BasicBlock] {
+        &self.basic_blocks
+    }
+
+    fn predecessor_count(&self, bb: usize) -> usize {
+        self.basic_blocks
+            .iter()
+            .filter(|basic_block| basic_block.successors.contains(&bb))
+            .count()
+    }
+
+    /// The natural loops of the function.
+    pub fn loops(&self) -> Vec<Loop> {
+        let successors: Vec<Vec<usize>> = self
+            .basic_blocks
+            .iter()
+            .map(|basic_block| basic_block.successors.clone())
+            .collect();
+
+        natural_loops(&successors)
+    }
+
+    /// Estimated cycles for one run of a basic block.
+    pub fn basic_block_cost(&self, bb: usize) -> f64 {
+        cost::lines_cost(self.lines(bb))
+    }
+
+    /// Estimated cycles for one iteration of a loop.
+    pub fn loop_cost(&self, l: &Loop) -> f64 {
+        l.body.iter().map(|bb| self.basic_block_cost(*bb)).sum()
+    }
+
+    /// Estimated cycles for one call of the function, with basic blocks
+    /// counted `LOOP_ITERATIONS` times for each loop they are in (up to
+    /// `MAX_LOOP_DEPTH` loops).
+    pub fn cost(&self) -> f64 {
+        let loops = self.loops();
+
+        (0..self.basic_blocks.len())
+            .map(|bb| {
+                let depth = loops.iter().filter(|l| l.body.contains(&bb)).count();
+                let depth = depth.min(MAX_LOOP_DEPTH);
+                self.basic_block_cost(bb) * LOOP_ITERATIONS.powi(depth as i32)
+            })
+            .sum()
+    }
+
+    /// The lines inside loop bodies, in order.
+    pub fn loop_lines(&self) -> Vec<&'a Line> {
+        let in_loops: BTreeSet<usize> = self.loops().into_iter().flat_map(|l| l.body).collect();
+
+        in_loops.into_iter().flat_map(|bb| self.lines(bb)).collect()
+    }
+
+    fn instruction_count(&self, bbs: &BTreeSet<usize>) -> usize {
+        bbs.iter()
+            .flat_map(|bb| self.lines(*bb))
+            .filter(|line| matches!(line, Line::Instruction(_)))
+            .count()
+    }
+
+    /// The line a basic block is shown at: its first line that isn't blank,
+    /// which is its label in LLVM IR and assembly sources.
+    fn header_line(&self, bb: usize) -> usize {
+        let basic_block = &self.basic_blocks[bb];
+
+        self.lines(bb)
+            .iter()
+            .position(|line| !matches!(line, Line::Blank))
+            .map_or(basic_block.start, |i| basic_block.start + i)
+    }
+
+    pub fn lines(&self, bb: usize) -> &'a [Line] {
+        let basic_block = &self.basic_blocks[bb];
+        &self.block.lines()[basic_block.start..basic_block.end]
+    }
+
+    pub fn print_dot(&self) {
+        println!("digraph cfg {{");
+        println!("  node [shape=box, fontname=monospace];");
+        println!(
+            "  label=\"{}\";",
+            call_graph::dot_escape(&CFG.name_policy().apply(self.block.demangled_label()))
+        );
+        for (bb, basic_block) in self.basic_blocks.iter().enumerate() {
+            let text: String = self
+                .lines(bb)
+                .iter()
+                .filter(|line| !matches!(line, Line::Blank))
+                .map(|line| format!("{}\\l", call_graph::dot_escape(line.as_str())))
+                .collect();
+            println!("  bb{} [label=\"bb{}:\\l{}\"];", bb, bb, text);
+
+            for successor in &basic_block.successors {
+                println!("  bb{} -> bb{};", bb, successor);
+            }
+        }
+        println!("}}");
+    }
+
+    pub fn print_json(&self) {
+        let nodes: Vec<String> = self
+            .basic_blocks
+            .iter()
+            .enumerate()
+            .map(|(bb, basic_block)| {
+                let instructions = self
+                    .lines(bb)
+                    .iter()
+                    .filter(|line| matches!(line, Line::Instruction(_)))
+                    .count();
+                format!(
+                    "    {{\"id\": {}, \"start\": {}, \"end\": {}, \"instructions\": {}, \"successors\": {:?}}}",
+                    bb, basic_block.start, basic_block.end, instructions, basic_block.successors
+                )
+            })
+            .collect();
+
+        println!("{{");
+        println!(
+            "  \"function\": {},",
+            call_graph::json_string(self.block.demangled_label())
+        );
+        println!("  \"basic_blocks\": [\n{}\n  ]", nodes.join(",\n"));
+        println!("}}");
+    }
+
+    /// Prints the lines of the function with the jumps inside it drawn in
+    /// the left margin, and the number of each basic block at its start.
+    pub fn print_text(&self) {
+        let margin = jump_margin(self.block.lines().len(), &self.jumps);
+
+        let name = CFG.name_policy().apply(self.block.demangled_label());
+        if CFG.show_cost {
+            println!("{} (cost {:.1})", name, self.cost());
+        } else {
+            println!("{}", name);
+        }
+        let bb_width = if CFG.show_cost { 12 } else { 5 };
+        for (index, (line, margin)) in self.block.lines().iter().zip(margin).enumerate() {
+            let bb_name = match (0..self.basic_blocks.len())
+                .find(|bb| self.header_line(*bb) == index)
+            {
+                Some(bb) if CFG.show_cost => format!("bb{} {:.1}", bb, self.basic_block_cost(bb)),
+                Some(bb) => format!("bb{}", bb),
+                None => String::new(),
+            };
+            let text = match line {
+                Line::Instruction(i) => match i.address() {
+                    Some(address) => format!("{:x}: {}", address, i.content()),
+                    None => format!("  {}", i.content()),
+                },
+                line => line.as_str().to_owned(),
+            };
+
+            println!("{} {:<bb_width$} {}", margin, bb_name, text);
+        }
+    }
+}
+
+/// The nodes of a graph (given by the successors of each node) reachable
+/// from node 0.
+fn reachable(successors: &[Vec<usize>]) -> Vec<bool> {
+    let mut reachable = vec![false; successors.len()];
+    let mut stack = vec![0];
+
+    while let Some(node) = stack.pop() {
+        if !reachable[node] {
+            reachable[node] = true;
+            stack.extend(&successors[node]);
+        }
+    }
+    reachable
+}
+
+/// For each node, the nodes that dominate it (those every path from node 0 to
+/// it goes through). Unreachable nodes have no dominators.
+fn dominators(successors: &[Vec<usize>]) -> Vec<BTreeSet<usize>> {
+    let count = successors.len();
+    let reachable = reachable(successors);
+    let all: BTreeSet<usize> = (0..count).filter(|node| reachable[*node]).collect();
+    let mut predecessors = vec![Vec::new(); count];
+    for (node, node_successors) in successors.iter().enumerate() {
+        for &successor in node_successors {
+            predecessors[successor].push(node);
+        }
+    }
+
+    let mut dominators: Vec<BTreeSet<usize>> = (0..count)
+        .map(|node| match node {
+            0 => BTreeSet::from([0]),
+            node if reachable[node] => all.clone(),
+            _ => BTreeSet::new(),
+        })
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for node in 1..count {
+            if !reachable[node] {
+                continue;
+            }
+
+            let mut new = predecessors[node]
+                .iter()
+                .filter(|p| reachable[**p])
+                .map(|p| dominators[*p].clone())
+                .reduce(|a, b| a.intersection(&b).copied().collect())
+                .unwrap_or_default();
+            new.insert(node);
+
+            if new != dominators[node] {
+                dominators[node] = new;
+                changed = true;
+            }
+        }
+    }
+
+    dominators
+}
+
+/// The natural loops of a graph: for each back edge (an edge to a node that
+/// dominates its source), the nodes that reach the source without going
+/// through the header. Loops sharing a header are merged.
+fn natural_loops(successors: &[Vec<usize>]) -> Vec<Loop> {
+    let dominators = dominators(successors);
+    let mut bodies: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+
+    for (node, node_successors) in successors.iter().enumerate() {
+        for &header in node_successors {
+            if !dominators[node].contains(&header) {
+                continue;
+            }
+
+            let body = bodies
+                .entry(header)
+                .or_insert_with(|| BTreeSet::from([header]));
+            let mut stack = vec![node];
+            while let Some(member) = stack.pop() {
+                if body.insert(member) {
+                    stack
+                        .extend((0..successors.len()).filter(|p| successors[*p].contains(&member)));
+                }
+            }
+        }
+    }
+
+    bodies
+        .into_iter()
+        .map(|(header, body)| Loop { header, body })
+        .collect()
+}
+
+/// Prints, for the functions of a section that have loops, the number of
+/// loops and, for each, its header basic block and the number of
+/// instructions in its body.
+pub fn print_loop_summary(section: &Section) {
+    let functions: Vec<(&Block, Vec<String>)> = section
+        .blocks()
+        .iter()
+        .filter_map(|block| {
+            let graph = ControlFlowGraph::new(block);
+            let sizes: Vec<String> = graph
+                .loops()
+                .iter()
+                .map(|l| format!("bb{}:{}", l.header, graph.instruction_count(&l.body)))
+                .collect();
+            (!sizes.is_empty()).then_some((block, sizes))
+        })
+        .collect();
+
+    println!(
+        "{} functions with loops in section {}:",
+        functions.len(),
+        section.name()
+    );
+    println!("loops / header:instructions of each loop / name");
+    for (block, sizes) in functions {
+        println!(
+            "  {:>5} {:<24} {}",
+            sizes.len(),
+            sizes.join(","),
+            CFG.name_policy().apply(block.demangled_label())
+        );
+    }
+}
+
+/// Prints the functions of a section by decreasing estimated cost, with the
+/// cost of an iteration of each of their loops.
+pub fn print_cost_summary(section: &Section) {
+    let mut functions: Vec<(&Block, f64, Vec<String>)> = section
+        .blocks()
+        .iter()
+        .map(|block| {
+            let graph = ControlFlowGraph::new(block);
+            let loops = graph
+                .loops()
+                .iter()
+                .map(|l| format!("bb{}:{:.1}", l.header, graph.loop_cost(l)))
+                .collect();
+            (block, graph.cost(), loops)
+        })
+        .collect();
+    functions.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    println!(
+        "{} blocks in section {} ({} cycles):",
+        functions.len(),
+        section.name(),
+        CFG.uarch
+    );
+    println!("cost / header:cost of each loop / name");
+    for (block, cost, loops) in functions {
+        println!(
+            "  {:>9.1} {:<24} {}",
+            cost,
+            loops.join(","),
+            CFG.name_policy().apply(block.demangled_label())
+        );
+    }
+}
+
+/// Pairs the basic blocks of two versions of a function. Pairs score by the
+/// similarity of the mnemonics of their straight-line instructions (branches
+/// belong to the graph, and change with the layout), with a bonus for equal
+/// numbers of predecessors and successors; entries always match.
+pub fn match_basic_blocks(
+    left: &ControlFlowGraph,
+    right: &ControlFlowGraph,
+    threshold: f64,
+) -> Vec<Match> {
+    let summarize = |graph: &ControlFlowGraph| {
+        (0..graph.basic_blocks.len())
+            .map(|bb| {
+                let mut histogram: HashMap<String, usize> = HashMap::new();
+                for line in graph.lines(bb) {
+                    match line {
+                        Line::Instruction(instruction) if Flow::of(instruction) == Flow::Next => {
+                            *histogram
+                                .entry(instruction.mnemonic().into_owned())
+                                .or_default() += 1;
+                        }
+                        _ => (),
+                    }
+                }
+                (
+                    histogram,
+                    graph.predecessor_count(bb),
+                    graph.basic_blocks[bb].successors.len(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let summaries1 = summarize(left);
+    let summaries2 = summarize(right);
+
+    let score = |l: usize, r: usize| {
+        let (histogram1, predecessors1, successors1) = &summaries1[l];
+        let (histogram2, predecessors2, successors2) = &summaries2[r];
+        let count1: usize = histogram1.values().sum();
+        let count2: usize = histogram2.values().sum();
+
+        if l == 0 && r == 0 {
+            return 1.0;
+        }
+        let content = match count1 + count2 {
+            0 => 1.0,
+            total => {
+                let common: usize = histogram1
+                    .iter()
+                    .map(|(line, n)| histogram2.get(line).map_or(0, |m| *n.min(m)))
+                    .sum();
+                2.0 * common as f64 / total as f64
+            }
+        };
+        let same = |a: &usize, b: &usize| if a == b { 0.1 } else { 0.0 };
+
+        (content + same(predecessors1, predecessors2) + same(successors1, successors2)).min(1.0)
+    };
+
+    let unmatched: Alignment = (0..left.basic_blocks.len())
+        .map(|l| (Some(l), None))
+        .chain((0..right.basic_blocks.len()).map(|r| (None, Some(r))))
+        .collect();
+    let mut matches = matching::match_leftovers(&unmatched, threshold, MatchKind::Content, score);
+    matches.sort_by_key(|m| m.left);
+    matches
+}
+
+/// The matches that are out of order: those left out of the longest run of
+/// matches whose right basic blocks are in increasing order, given matches
+/// sorted by left basic block.
+pub fn reordered(matches: &[Match]) -> HashSet<usize> {
+    // longest increasing subsequence of the right basic blocks
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; matches.len()];
+
+    for (i, m) in matches.iter().enumerate() {
+        let position = tails.partition_point(|&t| matches[t].right < m.right);
+        previous[i] = position.checked_sub(1).map(|p| tails[p]);
+        if position == tails.len() {
+            tails.push(i);
+        } else {
+            tails[position] = i;
+        }
+    }
+
+    let mut in_order = HashSet::new();
+    let mut current = tails.last().copied();
+    while let Some(i) = current {
+        in_order.insert(i);
+        current = previous[i];
+    }
+
+    (0..matches.len())
+        .filter(|i| !in_order.contains(i))
+        .map(|i| matches[i].left)
+        .collect()
+}
+
+/// Where branches inside a function land, by address (objdump) or by local
+/// label (assembly sources and LLVM IR).
+struct BranchTargets {
+    by_address: HashMap<u64, usize>,
+    by_label: HashMap<String, usize>,
+    numeric_labels: Vec<(usize, String)>,
+}
+
+impl BranchTargets {
+    fn new(lines: &[Line]) -> Self {
+        let mut targets = Self {
+            by_address: HashMap::new(),
+            by_label: HashMap::new(),
+            numeric_labels: Vec::new(),
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            match line {
+                Line::Instruction(instruction) => {
+                    if let Some(address) = instruction.address() {
+                        targets.by_address.insert(address, index);
+                    }
+                }
+                Line::LocalLabel(label) if label.parse::<u64>().is_ok() => {
+                    targets.numeric_labels.push((index, label.clone()));
+                }
+                Line::LocalLabel(label) => {
+                    targets.by_label.insert(label.clone(), index);
+                }
+                _ => (),
+            }
+        }
+
+        targets
+    }
+
+    /// The lines a branch at line `index` can go to inside the function.
+    fn resolve(&self, index: usize, instruction: &Instruction) -> Vec<usize> {
+        let ir_labels: Vec<usize> = RE_IR_LABEL
+            .captures_iter(instruction.content())
+            .filter_map(|caps| {
+                let label = caps[1].trim_matches('"');
+                self.by_label.get(label).copied()
+            })
+            .collect();
+        if !ir_labels.is_empty() {
+            return ir_labels;
+        }
+
+        let operands = instruction.operands();
+        let operand = match operands.first() {
+            Some(operand) => operand.as_str(),
+            None => return Vec::new(),
+        };
+
+        let target = if operand.contains('<') {
+            operand
+                .split_whitespace()
+                .next()
+                .and_then(|address| u64::from_str_radix(address, 16).ok())
+                .and_then(|address| self.by_address.get(&address).copied())
+        } else if let Some(label) = operand.strip_suffix('f') {
+            self.numeric_labels
+                .iter()
+                .find(|(line, name)| *line > index && name == label)
+                .map(|(line, _)| *line)
+        } else if let Some(label) = operand.strip_suffix('b') {
+            self.numeric_labels
+                .iter()
+                .rev()
+                .find(|(line, name)| *line < index && name == label)
+                .map(|(line, _)| *line)
+        } else {
+            self.by_label.get(operand).copied()
+        };
+
+        target.into_iter().collect()
+    }
+}
+
+/// Draws jumps as vertical lines in a margin, one string per line: `/` and
+/// `\` at the ends, `>` pointing at the target. Shorter jumps are drawn
+/// closer to the code.
+fn jump_margin(line_count: usize, jumps: &[(usize, usize)]) -> Vec<String> {
+    let mut by_length: Vec<&(usize, usize)> = jumps.iter().collect();
+    by_length.sort_by_key(|(from, to)| from.abs_diff(*to));
+
+    // levels[n]: the line ranges of the jumps drawn n columns from the code
+    let mut levels: Vec<Vec<(usize, usize, usize)>> = Vec::new();
+    for &&(from, to) in &by_length {
+        let (top, bottom) = (from.min(to), from.max(to));
+        let level = levels
+            .iter()
+            .position(|ranges| ranges.iter().all(|(t, b, _)| bottom < *t || top > *b))
+            .unwrap_or_else(|| {
+                levels.push(Vec::new());
+                levels.len() - 1
+            });
+        levels[level].push((top, bottom, to));
+    }
+
+    let columns = levels.len();
+    (0..line_count)
+        .map(|line| {
+            let mut cells = vec![' '; columns + 1];
+            let mut horizontal = None;
+            let mut is_target = false;
+
+            for (level, ranges) in levels.iter().enumerate() {
+                let column = columns - 1 - level;
+                for &(top, bottom, to) in ranges {
+                    if line == top || line == bottom {
+                        cells[column] = if line == top { '/' } else { '\\' };
+                        horizontal = Some(horizontal.map_or(column, |h: usize| h.min(column)));
+                        is_target |= line == to;
+                    } else if top < line && line < bottom {
+                        cells[column] = '|';
+                    }
+                }
+            }
+
+            if let Some(start) = horizontal {
+                for cell in &mut cells[start + 1..columns] {
+                    *cell = if *cell == '|' { '+' } else { '-' };
+                }
+                cells[columns] = if is_target { '>' } else { '-' };
+            }
+
+            cells.into_iter().collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llvm_ir;
+
+    // `a` is a target of the switch twice, apart
+    const LLVM_IR: &str = "
+define void @f(i32 %x) {
+start:
+  switch i32 %x, label %a [
+    i32 0, label %b
+    i32 1, label %a
+  ]
+
+a:
+  ret void
+
+b:
+  br label %a
+}
+";
+
+    #[test]
+    fn test_llvm_ir_basic_blocks() {
+        let asm = llvm_ir::read_llvm_ir_from_str(LLVM_IR, false);
+        let block = &asm.sections()[0].blocks()[0];
+        let graph = ControlFlowGraph::new(block);
+        let successors: Vec<&[usize]> = graph
+            .basic_blocks()
+            .iter()
+            .map(|basic_block| basic_block.successors.as_slice())
+            .collect();
+
+        assert_eq!(successors, [&[1, 2][..], &[], &[1]]);
+        assert_eq!(graph.predecessor_count(1), 2);
+
+        // each basic block is shown at its label, not at the blank line
+        // before it
+        let headers: Vec<&str> = (0..graph.basic_blocks().len())
+            .map(|bb| block.lines()[graph.header_line(bb)].as_str())
+            .collect();
+        assert_eq!(headers, ["start", "a", "b"]);
+    }
+
+    #[test]
+    fn test_loops() {
+        // 0 -> 1 -> 2 <-> 2 -> 3 -> 1, 3 -> 4; 5 is unreachable and jumps to 1
+        let successors = vec![vec![1], vec![2], vec![2, 3], vec![1, 4], vec![], vec![1]];
+        let loops = natural_loops(&successors);
+
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].header, 1);
+        assert_eq!(loops[0].body, BTreeSet::from([1, 2, 3]));
+        assert_eq!(loops[1].header, 2);
+        assert_eq!(loops[1].body, BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn test_reordered() {
+        let matches: Vec<Match> = [(0, 0), (1, 3), (2, 1), (3, 2), (4, 4)]
+            .iter()
+            .map(|&(left, right)| Match {
+                left,
+                right,
+                score: 1.0,
+                kind: MatchKind::Content,
+            })
+            .collect();
+
+        assert_eq!(reordered(&matches), HashSet::from([1]));
+    }
+
+    #[test]
+    fn test_jump_margin() {
+        // a loop from 3 back to 1 and a forward jump from 0 to 4
+        let margin = jump_margin(5, &[(3, 1), (0, 4)]);
+
+        assert_eq!(margin, vec!["/--", "|/>", "|| ", "|\\-", "\\->"]);
+    }
+}
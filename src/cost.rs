@@ -0,0 +1,260 @@
+use crate::line::{register_family, Line, OperandKind};
+use crate::CFG;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+static COST_TABLE: Lazy<CostTable> = Lazy::new(|| CostTable::parse(include_str!("cost_table.txt")));
+
+/// Cycles to add to an instruction's latency when it loads its operand.
+const LOAD_LATENCY: f64 = 4.0;
+
+/// Cost of instructions missing from the table.
+const DEFAULT_COST: Cost = Cost {
+    latency: 1.0,
+    throughput: 0.5,
+};
+
+/// Mnemonics that read their first operand without writing it.
+const COMPARISONS: [&str; 10] = [
+    "cmp", "test", "bt", "ucomiss", "ucomisd", "comiss", "comisd", "ptest", "push", "call",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cost {
+    latency: f64,
+    /// Reciprocal throughput: cycles between two independent instructions.
+    throughput: f64,
+}
+
+/// Latency and throughput of instructions on a few microarchitectures.
+struct CostTable {
+    uarches: Vec<String>,
+    costs: HashMap<String, Vec<Cost>>,
+}
+
+impl CostTable {
+    /// Parses a table with a header line naming the microarchitectures and a
+    /// line per mnemonic with `latency/throughput` for each of them. `#`
+    /// starts a comment.
+    fn parse(text: &str) -> Self {
+        let mut rows = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.split_whitespace());
+
+        let uarches = rows
+            .next()
+            .expect("bug: empty cost table")
+            .skip(1)
+            .map(str::to_owned)
+            .collect();
+        let costs = rows
+            .map(|mut row| {
+                let mnemonic = row.next().expect("bug: empty cost table row").to_owned();
+                let costs = row
+                    .map(|cell| {
+                        let (latency, throughput) =
+                            cell.split_once('/').expect("bug: wrong cost table cell");
+                        Cost {
+                            latency: latency.parse().expect("bug: wrong latency"),
+                            throughput: throughput.parse().expect("bug: wrong throughput"),
+                        }
+                    })
+                    .collect();
+                (mnemonic, costs)
+            })
+            .collect();
+
+        Self { uarches, costs }
+    }
+
+    fn uarch_index(&self, name: &str) -> Option<usize> {
+        self.uarches.iter().position(|uarch| uarch == name)
+    }
+
+    /// The cost of a mnemonic, falling back to its condition-code family or
+    /// its SSE form.
+    fn lookup(&self, uarch: usize, mnemonic: &str) -> Cost {
+        let family = match mnemonic {
+            "jmp" => mnemonic,
+            m if m.starts_with('j') => "jcc",
+            m if m.starts_with("cmov") => "cmovcc",
+            m if m.starts_with("set") && m.len() <= 6 => "setcc",
+            m => m,
+        };
+
+        [Some(family), family.strip_prefix('v')]
+            .into_iter()
+            .flatten()
+            .find_map(|m| self.costs.get(m))
+            .and_then(|costs| costs.get(uarch).copied())
+            .unwrap_or(DEFAULT_COST)
+    }
+
+    /// Estimates the cycles to run a straight sequence of instructions, given
+    /// as mnemonics and operands (destination first): the larger of the sum of
+    /// the reciprocal throughputs and the longest chain of latencies through
+    /// registers.
+    fn estimate<S: AsRef<str>>(&self, uarch: usize, instructions: &[(S, Vec<String>)]) -> f64 {
+        let mut throughput = 0.0;
+        let mut ready: HashMap<String, f64> = HashMap::new();
+        let mut chain: f64 = 0.0;
+
+        for (mnemonic, operands) in instructions {
+            let mnemonic = mnemonic.as_ref();
+            let cost = self.lookup(uarch, mnemonic);
+            throughput += cost.throughput;
+
+            let destination = operands.first().filter(|op| {
+                !COMPARISONS.contains(&mnemonic)
+                    && !mnemonic.starts_with('j')
+                    && OperandKind::of(op) != OperandKind::Memory
+            });
+            let write_only = destination.is_some()
+                && (["mov", "vmov", "lea", "cvt", "vcvt", "set", "pop"]
+                    .iter()
+                    .any(|prefix| mnemonic.starts_with(prefix))
+                    || (mnemonic.starts_with('v') && operands.len() >= 3));
+            let zero_idiom = operands.len() >= 2
+                && operands.iter().all(|op| *op == operands[0])
+                && [
+                    "xor", "sub", "pxor", "xorps", "xorpd", "vpxor", "vxorps", "vxorpd",
+                ]
+                .contains(&mnemonic);
+
+            let ready_at = |registers: Vec<String>| {
+                registers
+                    .iter()
+                    .map(|register| ready.get(register).copied().unwrap_or(0.0))
+                    .fold(0.0, f64::max)
+            };
+            let loads = mnemonic != "lea"
+                && operands.iter().enumerate().any(|(index, op)| {
+                    OperandKind::of(op) == OperandKind::Memory
+                        && !(index == 0 && mnemonic.starts_with("mov"))
+                });
+            let mut start = 0.0;
+            for (index, op) in operands.iter().enumerate() {
+                if OperandKind::of(op) == OperandKind::Memory {
+                    // the address is needed before the load
+                    let load = if loads { LOAD_LATENCY } else { 0.0 };
+                    start = f64::max(start, ready_at(registers(op)) + load);
+                } else if !zero_idiom && (index > 0 || !write_only) {
+                    start = f64::max(start, ready_at(registers(op)));
+                }
+            }
+            let end = start + cost.latency;
+
+            if let Some(register) = destination.and_then(|op| registers(op).into_iter().next()) {
+                ready.insert(register, end);
+            }
+            chain = chain.max(end);
+        }
+
+        throughput.max(chain)
+    }
+}
+
+/// The registers an operand mentions, with sub-registers (`eax`, `al`)
+/// named after the full register (`rax`).
+fn registers(operand: &str) -> Vec<String> {
+    operand
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter_map(register_family)
+        .collect()
+}
+
+/// Estimated cycles to run the instructions of a basic block on the
+/// microarchitecture of `--uarch`.
+pub fn lines_cost(lines: &[Line]) -> f64 {
+    let uarch = COST_TABLE.uarch_index(&CFG.uarch).expect("unknown --uarch");
+    let instructions: Vec<_> = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Instruction(i) => Some(i),
+            _ => None,
+        })
+        .map(|i| (i.mnemonic(), i.operands()))
+        .collect();
+
+    COST_TABLE.estimate(uarch, &instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instructions<'a>(text: &[(&'a str, &[&str])]) -> Vec<(&'a str, Vec<String>)> {
+        text.iter()
+            .map(|(mnemonic, operands)| {
+                (
+                    *mnemonic,
+                    operands.iter().map(|op| op.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let table = &COST_TABLE;
+        let skylake = table.uarch_index("skylake").unwrap();
+
+        assert_eq!(table.lookup(skylake, "add").latency, 1.0);
+        assert_eq!(table.lookup(skylake, "jne"), table.lookup(skylake, "jcc"));
+        assert_eq!(
+            table.lookup(skylake, "vpaddd"),
+            table.lookup(skylake, "paddd")
+        );
+        assert_eq!(table.lookup(skylake, "frobnicate"), DEFAULT_COST);
+        assert!(table.uarch_index("pentium").is_none());
+    }
+
+    #[test]
+    fn test_estimate() {
+        let table = &COST_TABLE;
+        let skylake = table.uarch_index("skylake").unwrap();
+
+        // independent: bound by throughput
+        let independent = instructions(&[
+            ("add", &["eax", "1"]),
+            ("add", &["ebx", "1"]),
+            ("add", &["ecx", "1"]),
+            ("add", &["edx", "1"]),
+        ]);
+        assert_eq!(table.estimate(skylake, &independent), 1.0);
+
+        // a chain through rax and rcx: 3 (imul) + 1 (add)
+        let chain = instructions(&[
+            ("imul", &["eax", "edi"]),
+            ("mov", &["ecx", "5"]),
+            ("add", &["rcx", "rax"]),
+        ]);
+        assert_eq!(table.estimate(skylake, &chain), 4.0);
+
+        // the load adds its latency, the zeroing idiom breaks the chain
+        let load = instructions(&[
+            ("mov", &["eax", "DWORD PTR [rdi]"]),
+            ("xor", &["eax", "eax"]),
+            ("add", &["eax", "1"]),
+        ]);
+        assert_eq!(table.estimate(skylake, &load), 5.0);
+
+        // loads from independent addresses overlap
+        let sum = instructions(&[
+            ("add", &["ebx", "DWORD PTR [rcx]"]),
+            ("add", &["ebx", "DWORD PTR [rcx+0x4]"]),
+            ("add", &["ebx", "DWORD PTR [rcx+0x8]"]),
+        ]);
+        assert_eq!(table.estimate(skylake, &sum), 7.0);
+    }
+
+    #[test]
+    fn test_registers() {
+        assert_eq!(registers("DWORD PTR [rdi+r8d*4+0x10]"), vec!["rdi", "r8"]);
+        assert_eq!(registers("0x8(rsp,rax,8)"), vec!["rsp", "rax"]);
+        assert_eq!(registers("ymm3"), vec!["v3"]);
+        assert!(registers("0x10").is_empty());
+    }
+}
@@ -1,4 +1,14 @@
 use crate::CFG;
+use std::borrow::Cow;
+
+/// Pairs of indices into the left and right sequences (`None` where one side
+/// has no counterpart).
+pub type Alignment = Vec<(Option<usize>, Option<usize>)>;
+
+pub const RED: &str = "\x1b[0;31m";
+pub const GREEN: &str = "\x1b[0;32m";
+pub const BLUE: &str = "\x1b[0;36m";
+pub const RESET: &str = "\x1b[0m";
 
 #[derive(Debug)]
 struct DiffCell(usize);
@@ -18,6 +28,7 @@ impl From<bool> for DiffCell {
 struct DiffTable(Vec<DiffCell>, usize);
 
 impl std::fmt::Debug for DiffTable {
+    #[allow(clippy::write_with_newline)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let right = self.1;
         let left = self.0.len() / right;
@@ -50,6 +61,7 @@ impl DiffTable {
         self.0[left * self.1 + right] = val;
     }
 
+    #[allow(clippy::needless_return)]
     fn find_max(&self, left: usize, right: usize) -> (usize, usize, usize) {
         let mut max = 0;
         let mut maxi = 0;
@@ -111,10 +123,18 @@ impl DiffTable {
     }
 }
 
-pub fn diff<T, F>(left: &Vec<T>, right: &Vec<T>, cmp: F) -> Vec<(Option<usize>, Option<usize>)>
+#[allow(clippy::needless_range_loop, clippy::let_and_return)]
+pub fn diff<T, F>(left: &[T], right: &[T], cmp: F) -> Vec<(Option<usize>, Option<usize>)>
 where
     F: Fn(&T, &T) -> bool,
 {
+    if left.is_empty() || right.is_empty() {
+        return (0..left.len())
+            .map(|i| (Some(i), None))
+            .chain((0..right.len()).map(|j| (None, Some(j))))
+            .collect();
+    }
+
     let mut matrix = DiffTable::new(left.len(), right.len());
 
     for i in 0..left.len() {
@@ -135,39 +155,37 @@ fn complete_diff(
     right: usize,
 ) -> Vec<(Option<usize>, Option<usize>)> {
     let mut diff = Vec::new();
-    let mut last_i = 0;
-    let mut last_j = 0;
+    let mut next_i = 0;
+    let mut next_j = 0;
 
     for (i, j) in indices {
-        while i - last_i > 1 {
-            diff.push((Some(last_i + 1), None));
-            last_i += 1;
-            continue;
+        while next_i < i {
+            diff.push((Some(next_i), None));
+            next_i += 1;
         }
-        while j - last_j > 1 {
-            diff.push((None, Some(last_j + 1)));
-            last_j += 1;
-            continue;
+        while next_j < j {
+            diff.push((None, Some(next_j)));
+            next_j += 1;
         }
         diff.push((Some(i), Some(j)));
-        last_i = i;
-        last_j = j;
+        next_i = i + 1;
+        next_j = j + 1;
     }
 
-    while last_i < left - 1 {
-        last_i += 1;
-        diff.push((Some(last_i), None));
+    while next_i < left {
+        diff.push((Some(next_i), None));
+        next_i += 1;
     }
 
-    while last_j < right - 1 {
-        last_j += 1;
-        diff.push((None, Some(last_j)));
+    while next_j < right {
+        diff.push((None, Some(next_j)));
+        next_j += 1;
     }
 
     diff
 }
 
-pub fn align<T, F>(left: &Vec<T>, right: &Vec<T>, compare: F) -> Vec<(Option<usize>, Option<usize>)>
+pub fn align<T, F>(left: &[T], right: &[T], compare: F) -> Vec<(Option<usize>, Option<usize>)>
 where
     F: Fn(&T, &T) -> bool,
 {
@@ -223,14 +241,23 @@ where
     result
 }
 
+/// Prints a line in a color, unless `--no-color`.
+pub fn print_colored(color: &str, line: std::fmt::Arguments) {
+    if CFG.no_color {
+        println!("{}", line);
+    } else {
+        println!("{}{}{}", color, line, RESET);
+    }
+}
+
 pub fn print_alignment<T, F, G>(
-    left: &Vec<T>,
-    right: &Vec<T>,
+    left: &[T],
+    right: &[T],
     alignment: Vec<(Option<usize>, Option<usize>)>,
     format: F,
     compare: G,
 ) where
-    F: Fn(&T) -> &str,
+    F: Fn(&T) -> Cow<str>,
     G: Fn(&T, &T) -> bool,
 {
     let show_adds = !CFG.only_dels;
@@ -238,55 +265,39 @@ pub fn print_alignment<T, F, G>(
     let show_small_change = !CFG.only_adds && !CFG.only_dels && !CFG.only_dels_and_adds;
     let show_match = !CFG.only_diff && show_small_change;
 
-    let color = !CFG.no_color;
-
-    let open_red = "\x1b[0;31m";
-    let open_green = "\x1b[0;32m";
-    let open_blue = "\x1b[0;36m";
-    let close = "\x1b[0m";
-
-    let width = 90;
+    let width = CFG.column_width();
     for (l, r) in alignment {
         match (l, r) {
             (Some(l), Some(r)) => {
                 let total_match = compare(&left[l], &right[r]);
-
-                if show_match || (show_small_change && !total_match) {
-                    if color && !total_match {
-                        print!("{}", open_blue);
-                    }
-                    println!(
-                        "{}\t{:<width$} {}\t{:<width$}",
-                        l,
-                        format(&left[l]),
-                        r,
-                        format(&right[r])
-                    );
-                    if color && !total_match {
-                        print!("{}", close);
-                    }
+                let line = format_args!(
+                    "{}\t{:<width$} {}\t{:<width$}",
+                    l,
+                    format(&left[l]),
+                    r,
+                    format(&right[r])
+                );
+
+                if total_match && show_match {
+                    println!("{}", line);
+                } else if !total_match && show_small_change {
+                    print_colored(BLUE, line);
                 }
             }
             (Some(l), None) => {
                 if show_dels {
-                    if color {
-                        print!("{}", open_red);
-                    }
-                    println!("{}\t{:<width$}  \t{:<width$}", l, format(&left[l]), "");
-                    if color {
-                        print!("{}", close);
-                    }
+                    print_colored(
+                        RED,
+                        format_args!("{}\t{:<width$}  \t{:<width$}", l, format(&left[l]), ""),
+                    );
                 }
             }
             (None, Some(r)) => {
                 if show_adds {
-                    if color {
-                        print!("{}", open_green);
-                    }
-                    println!(" \t{:<width$} {}\t{:<width$}", "", r, format(&right[r]));
-                    if color {
-                        print!("{}", close);
-                    }
+                    print_colored(
+                        GREEN,
+                        format_args!(" \t{:<width$} {}\t{:<width$}", "", r, format(&right[r])),
+                    );
                 }
             }
             (None, None) => println!(),
@@ -336,4 +347,30 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_diff_late_first_match() {
+        let a = vec!["x", "a", "b"];
+        let b = vec!["a", "b"];
+
+        assert_eq!(
+            diff(&a, &b, |c, d| c == d),
+            vec![(Some(0), None), (Some(1), Some(0)), (Some(2), Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_diff_empty() {
+        let a = vec![0, 1];
+        let b: Vec<i32> = vec![];
+
+        assert_eq!(
+            diff(&a, &b, |c, d| c == d),
+            vec![(Some(0), None), (Some(1), None)]
+        );
+        assert_eq!(
+            diff(&b, &a, |c, d| c == d),
+            vec![(None, Some(0)), (None, Some(1))]
+        );
+    }
 }
@@ -0,0 +1,293 @@
+use crate::line::Syntax;
+use crate::AsmFile;
+use crate::Line;
+
+// output sections a linker collects the per-function/per-object input
+// sections (e.g. `.text._ZN3foo3barE`) into
+const OUTPUT_SECTIONS: &[&str] = &[
+    ".text",
+    ".data.rel.ro",
+    ".rodata",
+    ".data",
+    ".bss",
+    ".tdata",
+    ".tbss",
+    ".gcc_except_table",
+];
+
+fn output_section(name: &str) -> &str {
+    OUTPUT_SECTIONS
+        .iter()
+        .find(|&&prefix| {
+            name == prefix
+                || name
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+        .copied()
+        .unwrap_or(name)
+}
+
+fn unquote(name: &str) -> &str {
+    name.trim()
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+        .unwrap_or_else(|| name.trim())
+}
+
+/// Cuts a line at the comment marker, ignoring markers inside strings.
+pub fn strip_comment(line: &str, marker: char) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if in_string => {
+                escaped = !escaped;
+                continue;
+            }
+            '"' if !escaped => in_string = !in_string,
+            c if c == marker && !in_string => return &line[..i],
+            _ => (),
+        }
+        escaped = false;
+    }
+
+    line
+}
+
+fn is_debug_directive(directive: &str) -> bool {
+    directive.starts_with(".cfi_") || directive == ".loc" || directive == ".file"
+}
+
+/// Reads assembly source as emitted by compilers for the GNU assembler (e.g.
+/// `rustc --emit asm`). Input sections are merged into the output section a
+/// linker would place them in, global symbols start new blocks and local
+/// (`.L`) labels are kept as markers inside the current block. Sections are
+/// switched like the assembler does, including `.pushsection`/`.popsection`
+/// and `.previous`. The syntax starts as AT&T and follows
+/// `.intel_syntax`/`.att_syntax` directives.
+pub fn read_gas_from_str(text: &str, strip_debug: bool) -> AsmFile {
+    let mut asm = AsmFile::new();
+    let mut syntax = Syntax::Att;
+    let mut in_debug_section = false;
+    // the current and previous sections, and those saved by `.pushsection`
+    let mut current = String::new();
+    let mut previous = String::new();
+    let mut pushed: Vec<(String, String)> = Vec::new();
+
+    for raw_line in text.split('\n') {
+        let line = strip_comment(raw_line, '#').trim();
+
+        if line.is_empty() {
+            if !in_debug_section {
+                asm.push(Line::Blank);
+            }
+            continue;
+        }
+
+        if let Some(label) = line.strip_suffix(':') {
+            let label = unquote(label);
+
+            if in_debug_section {
+                continue;
+            } else if label.starts_with(".L") || label.parse::<u64>().is_ok() {
+                asm.push(Line::LocalLabel(label.to_owned()));
+            } else {
+                asm.push(Line::label(label, ""));
+            }
+            continue;
+        }
+
+        let (word, rest) = match line.find(char::is_whitespace) {
+            Some(pos) => (&line[..pos], line[pos..].trim()),
+            None => (line, ""),
+        };
+
+        let name = || unquote(rest.split(',').next().unwrap_or("")).to_owned();
+        let sections = match word {
+            ".text" | ".data" | ".bss" => Some((word.to_owned(), current.clone())),
+            ".section" => Some((name(), current.clone())),
+            ".pushsection" => {
+                pushed.push((current.clone(), previous.clone()));
+                Some((name(), current.clone()))
+            }
+            ".popsection" => pushed.pop(),
+            ".previous" => Some((previous.clone(), current.clone())),
+            _ => None,
+        };
+
+        if let Some((section, previous_section)) = sections {
+            current = section;
+            previous = previous_section;
+            in_debug_section = strip_debug && current.starts_with(".debug_");
+            if !in_debug_section && !current.is_empty() {
+                asm.push(Line::section_header(output_section(&current)));
+            }
+            continue;
+        }
+
+        if in_debug_section || strip_debug && is_debug_directive(word) {
+            continue;
+        }
+
+        match word {
+            ".intel_syntax" => syntax = Syntax::Intel,
+            ".att_syntax" => syntax = Syntax::Att,
+            _ => (),
+        }
+
+        if word.starts_with('.') {
+            asm.push(Line::Other(line.to_owned()));
+        } else {
+            let content = match rest {
+                "" => String::new(),
+                rest => format!(
+                    "{:padding$} {}",
+                    "",
+                    rest,
+                    padding = 6_usize.saturating_sub(word.len())
+                ),
+            };
+            asm.push(Line::instruction(word, "", "", &content, raw_line, syntax));
+        }
+    }
+
+    asm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // shortened from `rustc --emit asm -C debuginfo=1`, with a `global_asm!`
+    // block in Intel syntax that switches sections
+    const RUSTC_ASM: &str = "\t.file\t\"a.4c2faf61c2d5dfd7-cgu.0\"
+\t.section\t.text._ZN1a5parse17h93caca7f32fbdaeeE,\"ax\",@progbits
+\t.p2align\t4
+\t.type\t_ZN1a5parse17h93caca7f32fbdaeeE,@function
+_ZN1a5parse17h93caca7f32fbdaeeE:
+.Lfunc_begin0:
+\t.cfi_startproc
+\t.loc\t1 3 0 prologue_end
+\ttestq\t%rsi, %rsi
+\tje\t.LBB0_2
+\tmovzbl\t(%rdi), %eax
+\tretq
+.LBB0_2:
+\txorl\t%eax, %eax
+\tretq
+.Lfunc_end0:
+\t.size\t_ZN1a5parse17h93caca7f32fbdaeeE, .Lfunc_end0-_ZN1a5parse17h93caca7f32fbdaeeE
+\t.cfi_endproc
+
+\t.pushsection\t.debug_gdb_scripts,\"aMS\",@progbits,1
+\t.byte\t1
+\t.asciz\t\"gdb.inlined-script\"
+\t.popsection
+
+\t.intel_syntax noprefix
+\t.section\t.rodata.table,\"a\",@progbits
+table:
+\t.quad\t1
+\t.previous
+\tmov\trax, qword ptr [rsp + 8]
+\t.att_syntax
+\t.section\t.text._ZN1a4main17h1854dee5fd9945b4E,\"ax\",@progbits
+_ZN1a4main17h1854dee5fd9945b4E:
+\tmovq\t8(%rsp), %rax
+\tcallq\t_ZN1a5parse17h93caca7f32fbdaeeE
+\tretq
+
+\t.section\t.debug_abbrev,\"\",@progbits
+\t.byte\t1
+";
+
+    fn instructions(block: &crate::asm_file::Block) -> Vec<&crate::line::Instruction> {
+        block
+            .lines()
+            .iter()
+            .filter_map(|line| match line {
+                Line::Instruction(i) => Some(i),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_rustc_asm() {
+        let asm = read_gas_from_str(RUSTC_ASM, true);
+        let names: Vec<&str> = asm.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(names, [".text", ".rodata"]);
+
+        let blocks = asm.get_section(".text").unwrap().blocks();
+        let labels: Vec<&str> = blocks.iter().map(|block| block.label()).collect();
+        assert_eq!(
+            labels,
+            [
+                "_ZN1a5parse17h93caca7f32fbdaeeE",
+                "_ZN1a4main17h1854dee5fd9945b4E"
+            ]
+        );
+
+        // local labels stay in their function, debug directives are dropped
+        let parse = blocks[0].lines();
+        assert!(parse.contains(&Line::LocalLabel(".LBB0_2".to_owned())));
+        assert!(!parse.iter().any(
+            |line| matches!(line, Line::Other(d) if d.starts_with(".cfi_") || d.starts_with(".loc"))
+        ));
+
+        // the last instruction is back in `parse` after `.popsection` and
+        // `.previous`, in Intel syntax
+        let parse = instructions(&blocks[0]);
+        let main = instructions(&blocks[1]);
+        let ops: Vec<&str> = parse.iter().map(|i| i.op()).collect();
+        assert_eq!(
+            ops,
+            ["testq", "je", "movzbl", "retq", "xorl", "retq", "mov"]
+        );
+        assert!(parse[6].same_as(main[0]));
+        assert_eq!(main.len(), 3);
+    }
+
+    #[test]
+    fn test_section_stack() {
+        let asm = read_gas_from_str(RUSTC_ASM, false);
+        let names: Vec<&str> = asm.sections().iter().map(|s| s.name()).collect();
+        assert_eq!(
+            names,
+            [".text", ".debug_gdb_scripts", ".rodata", ".debug_abbrev"]
+        );
+
+        let text = asm.get_section(".text").unwrap().blocks();
+        assert_eq!(instructions(&text[0]).len(), 7);
+        assert_eq!(instructions(&text[1]).len(), 3);
+    }
+
+    #[test]
+    fn test_output_section() {
+        assert_eq!(
+            output_section(".text._ZN1a4main17h1854dee5fd9945b4E"),
+            ".text"
+        );
+        assert_eq!(output_section(".text.unlikely._ZN5alloc7raw_vec"), ".text");
+        assert_eq!(output_section(".text"), ".text");
+        assert_eq!(output_section(".data.rel.ro..Lanon.1"), ".data.rel.ro");
+        assert_eq!(output_section(".rodata.str1.1"), ".rodata");
+        assert_eq!(output_section(".textual"), ".textual");
+        assert_eq!(output_section(".note.GNU-stack"), ".note.GNU-stack");
+    }
+
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(
+            strip_comment("\tmovq\t%rdi, %rax # comment", '#'),
+            "\tmovq\t%rdi, %rax "
+        );
+        assert_eq!(
+            strip_comment("\t.asciz\t\"#\\\"#\"", '#'),
+            "\t.asciz\t\"#\\\"#\""
+        );
+        assert_eq!(strip_comment("# whole line", '#'), "");
+    }
+}
@@ -0,0 +1,142 @@
+use crate::asm_file::Block;
+use crate::diff;
+use crate::line::Line;
+use crate::CFG;
+use std::collections::BTreeMap;
+
+/// Number of instructions per mnemonic.
+#[derive(Debug, Default)]
+pub struct Histogram(BTreeMap<String, usize>);
+
+impl Histogram {
+    pub fn of_blocks<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> Self {
+        Self::of_mnemonics(
+            blocks
+                .into_iter()
+                .flat_map(|block| block.lines())
+                .filter_map(|line| match line {
+                    Line::Instruction(i) => Some(i.mnemonic().into_owned()),
+                    _ => None,
+                }),
+        )
+    }
+
+    fn of_mnemonics<S: Into<String>>(mnemonics: impl IntoIterator<Item = S>) -> Self {
+        let mut counts = BTreeMap::new();
+        for mnemonic in mnemonics {
+            *counts.entry(mnemonic.into()).or_insert(0) += 1;
+        }
+        Self(counts)
+    }
+
+    fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// Mnemonics by decreasing count.
+    fn sorted(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<(&str, usize)> = self
+            .0
+            .iter()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        counts
+    }
+
+    /// Changes in count from `self` to `other`, by decreasing size of the
+    /// change.
+    fn deltas<'a>(&'a self, other: &'a Self) -> Vec<(&'a str, i64)> {
+        let count = |histogram: &Self, mnemonic: &str| {
+            histogram.0.get(mnemonic).copied().unwrap_or(0) as i64
+        };
+
+        let mut deltas: Vec<(&str, i64)> = self
+            .0
+            .keys()
+            .chain(other.0.keys().filter(|m| !self.0.contains_key(*m)))
+            .map(|mnemonic| {
+                (
+                    mnemonic.as_str(),
+                    count(other, mnemonic) - count(self, mnemonic),
+                )
+            })
+            .filter(|(_, delta)| *delta != 0)
+            .collect();
+        deltas.sort_by(|a, b| {
+            b.1.abs()
+                .cmp(&a.1.abs())
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.0.cmp(b.0))
+        });
+        deltas
+    }
+
+    pub fn print(&self, scope: &str) {
+        let total = self.total();
+
+        println!(
+            "{} instructions, {} mnemonics in {}:",
+            total,
+            self.0.len(),
+            scope
+        );
+        println!("count / percentage / mnemonic");
+        for (mnemonic, count) in self.sorted() {
+            println!(
+                "  {:>8} {:>6.2}% {}",
+                count,
+                count as f64 * 100.0 / total as f64,
+                mnemonic
+            );
+        }
+    }
+
+    pub fn print_diff(&self, other: &Self) {
+        let deltas = self.deltas(other);
+
+        for &(mnemonic, delta) in &deltas {
+            if (delta > 0 && CFG.only_dels) || (delta < 0 && CFG.only_adds) {
+                continue;
+            }
+
+            diff::print_colored(
+                if delta > 0 { diff::GREEN } else { diff::RED },
+                format_args!(
+                    "  {:>+6} {:<12} {} -> {}",
+                    delta,
+                    mnemonic,
+                    self.0.get(mnemonic).unwrap_or(&0),
+                    other.0.get(mnemonic).unwrap_or(&0)
+                ),
+            );
+        }
+
+        println!();
+        println!(
+            "{} mnemonics changed, {} -> {} instructions ({:+})",
+            deltas.len(),
+            self.total(),
+            other.total(),
+            other.total() as i64 - self.total() as i64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deltas() {
+        let left = Histogram::of_mnemonics(["mov", "mov", "add", "div", "call", "ret"]);
+        let right = Histogram::of_mnemonics(["mov", "vpaddd", "vpaddd", "vpaddd", "add", "ret"]);
+
+        assert_eq!(left.sorted()[0], ("mov", 2));
+        assert_eq!(
+            left.deltas(&right),
+            vec![("vpaddd", 3), ("call", -1), ("div", -1), ("mov", -1)]
+        );
+        assert!(left.deltas(&left).is_empty());
+    }
+}
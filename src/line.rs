@@ -1,10 +1,17 @@
+use crate::symbol;
 use crate::CFG;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::borrow::Cow;
 
 #[derive(Debug, PartialEq)]
-pub struct Offset(String);
+pub struct Offset(Option<u64>);
+
+impl Offset {
+    fn from_hex(offset: &str) -> Self {
+        Self(u64::from_str_radix(offset, 16).ok())
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Label(String, String, Offset);
@@ -17,10 +24,45 @@ impl Label {
     pub fn name(&self) -> &str {
         &self.1
     }
+
+    pub fn address(&self) -> Option<u64> {
+        self.2 .0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    Intel,
+    Att,
+    /// Code that is not x86 assembly (e.g. LLVM IR, or `--syntax none` for
+    /// binaries of other architectures), whose ops and operands are used as
+    /// they are.
+    Other,
+}
+
+impl Syntax {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "intel" => Some(Self::Intel),
+            "att" => Some(Self::Att),
+            "none" => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    /// The `-M` option selecting the syntax in objdump, which only x86
+    /// syntaxes have.
+    pub fn objdump_option(&self) -> Option<&str> {
+        match self {
+            Self::Intel => Some("-Mintel"),
+            Self::Att => Some("-Matt"),
+            Self::Other => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Instruction(String, Offset, String, String);
+pub struct Instruction(String, Offset, String, String, Syntax, Vec<u8>);
 
 impl Instruction {
     pub fn op(&self) -> &str {
@@ -30,6 +72,357 @@ impl Instruction {
     pub fn content(&self) -> &str {
         &self.2
     }
+
+    pub fn address(&self) -> Option<u64> {
+        self.1 .0
+    }
+
+    /// The encoding of the instruction, if the disassembly shows it.
+    pub fn bytes(&self) -> &[u8] {
+        &self.5
+    }
+
+    /// The size of the encoding in bytes (0 if unknown).
+    pub fn size(&self) -> usize {
+        self.5.len()
+    }
+
+    pub fn bytes_str(&self) -> String {
+        self.5
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The op without syntax-specific decorations (e.g. AT&T size suffixes), so
+    /// that it can be compared between AT&T and Intel disassemblies.
+    pub fn mnemonic(&self) -> Cow<'_, str> {
+        match self.4 {
+            Syntax::Intel | Syntax::Other => Cow::Borrowed(&self.0),
+            Syntax::Att => att_mnemonic(&self.0, self.operand_str()),
+        }
+    }
+
+    /// The operands in Intel order (destination first), without the AT&T
+    /// register, immediate and indirection prefixes. Memory operands of both
+    /// syntaxes are written as `seg:[base+index*scale+disp]`, without their
+    /// size.
+    pub fn operands(&self) -> Vec<String> {
+        let operands = split_operands(self.operand_str());
+
+        match self.4 {
+            Syntax::Other => operands.into_iter().map(str::to_owned).collect(),
+            Syntax::Intel => operands
+                .into_iter()
+                .map(|op| intel_memory(op).unwrap_or_else(|| op.to_owned()))
+                .collect(),
+            Syntax::Att => operands
+                .into_iter()
+                .rev()
+                .map(|op| {
+                    let op = op.replace(['%', '$'], "");
+                    att_memory(&op).unwrap_or(op)
+                })
+                .collect(),
+        }
+    }
+
+    /// The comment after the operands, where objdump shows the targets of
+    /// indirect and RIP-relative operands (`# 4010 <foo>`).
+    pub fn comment(&self) -> Option<&str> {
+        let operands = &self.2[self.0.len()..];
+
+        operands.find('#').map(|pos| operands[pos + 1..].trim())
+    }
+
+    /// Whether two instructions are the same, in either syntax: same mnemonic,
+    /// operands and comment.
+    pub fn same_as(&self, other: &Instruction) -> bool {
+        self.mnemonic() == other.mnemonic()
+            && self.operands() == other.operands()
+            && self.comment() == other.comment()
+    }
+
+    fn operand_str(&self) -> &str {
+        let operands = &self.2[self.0.len()..];
+
+        match operands.find('#') {
+            Some(pos) => operands[..pos].trim(),
+            None => operands.trim(),
+        }
+    }
+}
+
+fn split_operands(operands: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0_i32;
+    let mut start = 0;
+
+    for (i, c) in operands.char_indices() {
+        match c {
+            '(' | '[' | '<' | '{' => depth += 1,
+            ')' | ']' | '>' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if !operands[start..].trim().is_empty() {
+        result.push(operands[start..].trim());
+    }
+
+    result
+}
+
+/// A memory operand in Intel syntax (`QWORD PTR [rsp+rax*1+0x8]`, or
+/// `dword ptr [rdi + 4*rsi]` in assembly sources) in the form shared by both
+/// syntaxes.
+fn intel_memory(operand: &str) -> Option<String> {
+    let operand = RE_INTEL_SIZE.replace(operand, "");
+
+    if !operand.ends_with(']') {
+        // `QWORD PTR fs:0x28`
+        return Some(operand.into_owned());
+    }
+    let (segment, address) = operand[..operand.len() - 1].split_once('[')?;
+
+    Some(memory(segment.strip_suffix(':'), &split_terms(address)))
+}
+
+/// A memory operand in AT&T syntax, without the register prefixes
+/// (`0x8(rsp,rax,1)`, `*foo(rip)`, `*foo`), in the form shared by both
+/// syntaxes.
+fn att_memory(operand: &str) -> Option<String> {
+    if let Some(caps) = RE_ATT_MEMORY_PARTS.captures(operand) {
+        let mut terms = split_terms(caps.get(3).map_or("", |m| m.as_str()));
+        let mut registers = Vec::new();
+        if let Some(base) = caps.get(4).filter(|m| !m.as_str().is_empty()) {
+            registers.push(('+', base.as_str().to_owned()));
+        }
+        if let Some(index) = caps.get(5).filter(|m| !m.as_str().is_empty()) {
+            let scale = caps.get(6).map_or("1", |m| m.as_str());
+            registers.push(('+', format!("{}*{}", index.as_str(), scale)));
+        }
+        registers.append(&mut terms);
+
+        return Some(memory(caps.get(2).map(|m| m.as_str()), &registers));
+    }
+
+    // an indirect call or jump through memory at an address or symbol
+    let target = operand.strip_prefix('*')?;
+    Some(if register_family(target).is_some() {
+        target.to_owned()
+    } else {
+        memory(None, &split_terms(target))
+    })
+}
+
+/// The terms of an address, with their signs (`rsp + 4*rsi - 8`).
+fn split_terms(address: &str) -> Vec<(char, String)> {
+    let mut terms = Vec::new();
+    let mut sign = '+';
+    let mut term = String::new();
+
+    for c in address.chars() {
+        match c {
+            '+' | '-' => {
+                if !term.trim().is_empty() {
+                    terms.push((sign, term.trim().to_owned()));
+                }
+                sign = c;
+                term.clear();
+            }
+            c => term.push(c),
+        }
+    }
+    if !term.trim().is_empty() {
+        terms.push((sign, term.trim().to_owned()));
+    }
+
+    terms
+}
+
+/// A memory operand from the terms of its address, as
+/// `seg:[base+index*scale+symbol+disp]`: the scale always shown, numbers
+/// added up and written in hexadecimal, and a zero displacement left out.
+fn memory(segment: Option<&str>, terms: &[(char, String)]) -> String {
+    let is_register = |term: &str| register_family(term).is_some() || term == "rip";
+    let number = |term: &str| match term.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => term.parse::<i64>().ok(),
+    };
+
+    let (mut base, mut index) = (None, None);
+    let mut symbols = String::new();
+    let mut displacement = 0_i64;
+    for (sign, term) in terms {
+        if let Some((a, b)) = term.split_once('*') {
+            let (a, b) = (a.trim(), b.trim());
+            index = Some(if is_register(a) {
+                format!("{}*{}", a, b)
+            } else {
+                format!("{}*{}", b, a)
+            });
+        } else if is_register(term) && base.is_none() {
+            base = Some(term.clone());
+        } else if is_register(term) {
+            index = Some(format!("{}*1", term));
+        } else if let Some(value) = number(term) {
+            displacement += if *sign == '-' { -value } else { value };
+        } else {
+            symbols.push(*sign);
+            symbols.push_str(term);
+        }
+    }
+
+    let mut address = [base, index]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("+");
+    address.push_str(if address.is_empty() {
+        symbols.trim_start_matches('+')
+    } else {
+        &symbols
+    });
+    match displacement {
+        0 if !address.is_empty() => (),
+        d if d < 0 => address.push_str(&format!("-0x{:x}", d.unsigned_abs())),
+        d if address.is_empty() => address.push_str(&format!("0x{:x}", d)),
+        d => address.push_str(&format!("+0x{:x}", d)),
+    }
+
+    match segment {
+        Some(segment) => format!("{}:[{}]", segment, address),
+        None => format!("[{}]", address),
+    }
+}
+
+/// What an operand of an x86 instruction refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    Register,
+    Memory,
+    /// An immediate, an address or a symbol.
+    Other,
+}
+
+impl OperandKind {
+    /// The kind of an operand in either syntax, with or without the AT&T
+    /// prefixes (`*` of indirect calls and jumps, `%` of registers).
+    pub fn of(operand: &str) -> Self {
+        let operand = operand.trim().trim_start_matches('*');
+
+        if operand.contains('[') || RE_ATT_MEMORY.is_match(operand) {
+            OperandKind::Memory
+        } else if register_family(operand.trim_start_matches('%')).is_some() {
+            OperandKind::Register
+        } else {
+            OperandKind::Other
+        }
+    }
+}
+
+/// The register a register name is part of, with sub-registers (`eax`, `al`)
+/// named after the full register (`rax`) and vector registers after their
+/// number (`v3` for `xmm3`, `ymm3` and `zmm3`).
+pub fn register_family(name: &str) -> Option<String> {
+    let legacy = match name {
+        "rax" | "eax" | "ax" | "al" | "ah" => Some("rax"),
+        "rbx" | "ebx" | "bx" | "bl" | "bh" => Some("rbx"),
+        "rcx" | "ecx" | "cx" | "cl" | "ch" => Some("rcx"),
+        "rdx" | "edx" | "dx" | "dl" | "dh" => Some("rdx"),
+        "rsi" | "esi" | "si" | "sil" => Some("rsi"),
+        "rdi" | "edi" | "di" | "dil" => Some("rdi"),
+        "rbp" | "ebp" | "bp" | "bpl" => Some("rbp"),
+        "rsp" | "esp" | "sp" | "spl" => Some("rsp"),
+        _ => None,
+    };
+    if let Some(family) = legacy {
+        return Some(family.to_owned());
+    }
+
+    let numbered = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .map(|rest| rest.trim_end_matches(['d', 'w', 'b']))
+            .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+    };
+    if let Some(number) = numbered("r") {
+        Some(format!("r{}", number))
+    } else {
+        ["xmm", "ymm", "zmm"]
+            .iter()
+            .find_map(|prefix| numbered(prefix))
+            .map(|number| format!("v{}", number))
+    }
+}
+
+// ops that take a b/w/l/q operand size suffix in AT&T syntax
+const ATT_SUFFIXED: &[&str] = &[
+    "adc", "add", "and", "bsf", "bsr", "bt", "btc", "btr", "bts", "call", "cmp", "cmps", "cmpxchg",
+    "dec", "div", "enter", "idiv", "imul", "in", "inc", "iret", "jmp", "lea", "leave", "lods",
+    "lzcnt", "mov", "movabs", "movnti", "movs", "mul", "neg", "nop", "not", "or", "out", "pop",
+    "popcnt", "popf", "push", "pushf", "rcl", "rcr", "ret", "rol", "ror", "sal", "sar", "sbb",
+    "scas", "shl", "shld", "shr", "shrd", "stos", "sub", "test", "tzcnt", "xadd", "xchg", "xor",
+];
+
+const CONDITION_CODES: &[&str] = &[
+    "a", "ae", "b", "be", "c", "e", "g", "ge", "l", "le", "na", "nae", "nb", "nbe", "nc", "ne",
+    "ng", "nge", "nl", "nle", "no", "np", "ns", "nz", "o", "p", "pe", "po", "s", "z",
+];
+
+fn att_mnemonic<'a>(op: &'a str, operands: &str) -> Cow<'a, str> {
+    let renamed = match op {
+        "cbtw" => Some("cbw"),
+        "cwtl" => Some("cwde"),
+        "cltq" => Some("cdqe"),
+        "cwtd" => Some("cwd"),
+        "cltd" => Some("cdq"),
+        "cqto" => Some("cqo"),
+        "movslq" => Some("movsxd"),
+        _ => None,
+    };
+    if let Some(renamed) = renamed {
+        return Cow::Borrowed(renamed);
+    }
+
+    let bytes = op.as_bytes();
+    if bytes.len() == 6
+        && (op.starts_with("movz") || op.starts_with("movs"))
+        && matches!(bytes[4], b'b' | b'w')
+        && matches!(bytes[5], b'w' | b'l' | b'q')
+    {
+        return Cow::Owned(format!("{}x", &op[..4]));
+    }
+
+    // `movq`/`movd` between general purpose and vector registers keep their
+    // name in Intel syntax
+    if op == "movq" && operands.contains("mm") {
+        return Cow::Borrowed(op);
+    }
+
+    let stem = match op.strip_suffix(['b', 'w', 'l', 'q']) {
+        Some(stem) => stem,
+        None => return Cow::Borrowed(op),
+    };
+
+    let suffixed = ATT_SUFFIXED.contains(&stem)
+        || stem
+            .strip_prefix("cmov")
+            .is_some_and(|cc| CONDITION_CODES.contains(&cc))
+        || stem.starts_with("cvt") && stem.ends_with("2sd")
+        || stem.starts_with("cvt") && stem.ends_with("2ss")
+        || stem.starts_with("cvt") && stem.ends_with("2si");
+
+    if suffixed {
+        Cow::Borrowed(stem)
+    } else {
+        Cow::Borrowed(op)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -44,21 +437,46 @@ impl SectionHeader {
 static RE_HASH: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"::h[a-f0-9]{16}").expect("bug: wrong regex"));
 
-fn demangle(id: &str) -> String {
-    let st = rustc_demangle::demangle(id).to_string();
+/// Demangles a Rust (legacy or v0) or C++ (Itanium) symbol, leaving it as it
+/// is if it is neither.
+fn demangle_symbol(id: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(id) {
+        return demangled.to_string();
+    }
+
+    // clone suffixes (e.g. `.cold`) are kept as they are, like for Rust
+    let (mangled, suffix) = id.split_at(id.find('.').unwrap_or(id.len()));
+    // Mach-O symbols have an extra leading underscore
+    let mangled = mangled
+        .strip_prefix("__Z")
+        .map_or(mangled, |_| &mangled[1..]);
+    cpp_demangle::Symbol::new(mangled)
+        .ok()
+        .and_then(|symbol| symbol.demangle(&Default::default()).ok())
+        .map_or_else(|| id.to_owned(), |demangled| demangled + suffix)
+}
+
+fn demangle(id: &str, no_hash: bool) -> String {
+    let st = demangle_symbol(id);
 
-    if CFG.no_hash {
-        RE_HASH.replace(&st, |_: &regex::Captures| "").to_string()
+    if no_hash {
+        RE_HASH.replace_all(&st, "").into_owned()
     } else {
         st
     }
 }
 
+/// Demangles every symbol in the text.
+pub fn demangle_all(text: &str, no_hash: bool) -> Cow<'_, str> {
+    RE_SYM.replace_all(text, |caps: &regex::Captures| demangle(&caps[0], no_hash))
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Line {
     Label(Label),
     Instruction(Instruction),
     SectionHeader(SectionHeader),
+    LocalLabel(String),
     Blank,
     Other(String),
 }
@@ -70,25 +488,52 @@ static RE_LABEL: Lazy<Regex> =
     Lazy::new(|| Regex::new("([0-9a-f][0-9a-f]*) <(.*)>:").expect("bug: wrong regex"));
 
 static RE_INSTR: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(" [ ]*([0-9a-f][0-9a-f]*):\t([a-z][a-z0-9]*)(.*)").expect("bug: wrong regex")
+    Regex::new(
+        " [ ]*([0-9a-f][0-9a-f]*):\t(?:([0-9a-f]{2}(?: [0-9a-f]{2})*) *\t)?([a-z][a-z0-9._]*)(.*)",
+    )
+    .expect("bug: wrong regex")
+});
+
+// the rest of the encoding of a long instruction, which objdump puts on the
+// next line without `-w`
+static RE_BYTES_CONTINUATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^ [ ]*[0-9a-f]+:\t([0-9a-f]{2}(?: [0-9a-f]{2})*) *$").expect("bug: wrong regex")
+});
+
+// `disp(base,index,scale)` memory operands in AT&T syntax
+static RE_ATT_MEMORY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\((?:%?[a-z][a-z0-9]*)?(?:,%?[a-z][a-z0-9]*)?(?:,[1248])?\)$")
+        .expect("bug: wrong regex")
+});
+
+// `disp(base,index,scale)` memory operands in AT&T syntax without register
+// prefixes, with the segment, displacement, base, index and scale
+static RE_ATT_MEMORY_PARTS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\*?(([a-z]s):)?([^(]*)\(([a-z][a-z0-9]*)?(?:,([a-z][a-z0-9]*)(?:,([1248]))?)?\)$")
+        .expect("bug: wrong regex")
 });
 
-// taken from the `rustfilt` crate
+// the size of memory operands in Intel syntax
+static RE_INTEL_SIZE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^[a-z]+ ptr\s+").expect("bug: wrong regex"));
+
+// adapted from the `rustfilt` crate to also match C++ symbols (`_Z`)
 static RE_SYM: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"_(ZN|R)[\$\._[:alnum:]]*").expect("bug: wrong regex"));
+    Lazy::new(|| Regex::new(r"_?_(Z|R)[\$\._[:alnum:]]*").expect("bug: wrong regex"));
 
 impl Line {
     pub fn as_str(&self) -> &str {
         match self {
             Self::Label(l) => &l.0,
-            Self::Instruction(i) => &i.2,
+            Self::Instruction(i) => i.content(),
             Self::SectionHeader(h) => &h.0,
+            Self::LocalLabel(l) => l,
             Self::Blank => "",
-            Self::Other(o) => &o,
+            Self::Other(o) => o,
         }
     }
 
-    pub fn from_str(string: &str) -> Self {
+    pub fn from_str(string: &str, syntax: Syntax) -> Self {
         if RE_HEADER.is_match(string) {
             let cap = RE_HEADER.captures(string).unwrap().get(1).unwrap().as_str();
             Self::section_header(cap)
@@ -98,12 +543,16 @@ impl Line {
             Self::label(name, offset)
         } else if RE_INSTR.is_match(string) {
             let offset = RE_INSTR.captures(string).unwrap().get(1).unwrap().as_str();
-            let name = RE_INSTR.captures(string).unwrap().get(2).unwrap().as_str();
-            let content = match RE_INSTR.captures(string).unwrap().get(3) {
+            let bytes = match RE_INSTR.captures(string).unwrap().get(2) {
                 Some(val) => val.as_str(),
                 _ => "",
             };
-            Self::instruction(name, offset, content, string)
+            let name = RE_INSTR.captures(string).unwrap().get(3).unwrap().as_str();
+            let content = match RE_INSTR.captures(string).unwrap().get(4) {
+                Some(val) => val.as_str(),
+                _ => "",
+            };
+            Self::instruction(name, offset, bytes, content, string, syntax)
         } else if string.trim().is_empty() {
             Self::Blank
         } else {
@@ -111,29 +560,329 @@ impl Line {
         }
     }
 
-    fn section_header(name: &str) -> Self {
+    pub fn section_header(name: &str) -> Self {
         Self::SectionHeader(SectionHeader(name.to_owned()))
     }
 
-    fn label(name: &str, offset: &str) -> Self {
-        let demangled_name: Cow<str> =
-            RE_SYM.replace(name, |caps: &regex::Captures| demangle(&caps[0]));
+    pub fn label(name: &str, offset: &str) -> Self {
+        let demangled_name = demangle_all(name, CFG.no_hash);
         Self::Label(Label(
-            demangled_name.into_owned(),
+            symbol::normalize_suffixes(&demangled_name).into_owned(),
             name.to_owned(),
-            Offset(offset.to_owned()),
+            Offset::from_hex(offset),
         ))
     }
 
-    fn instruction(name: &str, offset: &str, content: &str, line: &str) -> Self {
-        //let line: Cow<str> = RE_SYM.replace(line, |caps: &regex::Captures| demangle(&caps[0]));
-        let content: Cow<str> =
-            RE_SYM.replace(content, |caps: &regex::Captures| demangle(&caps[0]));
+    pub fn instruction(
+        name: &str,
+        offset: &str,
+        bytes: &str,
+        content: &str,
+        line: &str,
+        syntax: Syntax,
+    ) -> Self {
+        let content = demangle_all(content, CFG.no_hash);
         Self::Instruction(Instruction(
             name.to_owned(),
-            Offset(offset.to_owned()),
-            format!("{}{}", name, content),
+            Offset::from_hex(offset),
+            format!("{}{}", name, symbol::normalize_suffixes(&content)),
             line.to_owned(),
+            syntax,
+            parse_bytes(bytes),
         ))
     }
 }
+
+fn parse_bytes(bytes: &str) -> Vec<u8> {
+    bytes
+        .split_whitespace()
+        .filter_map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
+/// Splits objdump output into lines, moving the bytes of continuation lines
+/// (printed for long instructions without `-w`) to the line of their
+/// instruction.
+pub fn join_continued_bytes(text: &str) -> Vec<Cow<'_, str>> {
+    let mut lines: Vec<Cow<str>> = Vec::new();
+
+    for line in text.split('\n') {
+        let continued = RE_BYTES_CONTINUATION.captures(line).and_then(|caps| {
+            let previous = lines.last()?;
+            let bytes_end = previous.match_indices('\t').nth(1)?.0;
+            let bytes = caps.get(1)?.as_str();
+            Some(format!(
+                "{} {} {}",
+                previous[..bytes_end].trim_end(),
+                bytes,
+                &previous[bytes_end..]
+            ))
+        });
+
+        match continued {
+            Some(joined) => *lines.last_mut().unwrap() = Cow::Owned(joined),
+            None => lines.push(Cow::Borrowed(line)),
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(op: &str, content: &str, syntax: Syntax) -> Instruction {
+        Instruction(
+            op.to_owned(),
+            Offset(None),
+            content.to_owned(),
+            content.to_owned(),
+            syntax,
+            Vec::new(),
+        )
+    }
+
+    /// An instruction parsed from an objdump line, without the parts that
+    /// depend on the configuration.
+    fn parsed(line: &str) -> Instruction {
+        let caps = RE_INSTR.captures(line).unwrap();
+        let bytes = caps.get(2).map_or("", |m| m.as_str());
+
+        Instruction(
+            caps[3].to_owned(),
+            Offset::from_hex(&caps[1]),
+            format!("{}{}", &caps[3], &caps[4]),
+            line.to_owned(),
+            Syntax::Intel,
+            parse_bytes(bytes),
+        )
+    }
+
+    #[test]
+    fn test_bytes() {
+        let i = parsed("   1422e:\t48 83 3d 6a 0f 04 00 00 \tcmp    QWORD PTR [rip+0x40f6a],0x0");
+        assert_eq!(i.bytes(), &[0x48, 0x83, 0x3d, 0x6a, 0x0f, 0x04, 0x00, 0x00]);
+        assert_eq!(i.size(), 8);
+        assert_eq!(i.bytes_str(), "48 83 3d 6a 0f 04 00 00");
+        assert_eq!(i.address(), Some(0x1422e));
+
+        // objdump output without the bytes
+        let i = parsed("   1422e:\tret");
+        assert!(i.bytes().is_empty());
+        assert_eq!(i.size(), 0);
+        assert_eq!(i.bytes_str(), "");
+    }
+
+    #[test]
+    fn test_join_continued_bytes() {
+        let text = "   142a8:\t49 bd 00 00 00 00 00 \tmovabs r13,0x8000000000000000\n   142af:\t00 00 80 \n   142b2:\tc3                   \tret";
+        let lines = join_continued_bytes(text);
+
+        assert_eq!(lines.len(), 2);
+        let movabs = parsed(&lines[0]);
+        assert_eq!(movabs.size(), 10);
+        assert_eq!(movabs.bytes_str(), "49 bd 00 00 00 00 00 00 00 80");
+        assert_eq!(movabs.content(), "movabs r13,0x8000000000000000");
+        assert_eq!(parsed(&lines[1]).bytes(), &[0xc3]);
+
+        // a continuation without an instruction before it stays as it is
+        assert_eq!(
+            join_continued_bytes("   142af:\t00 00 80 "),
+            vec!["   142af:\t00 00 80 "]
+        );
+    }
+
+    #[test]
+    fn test_operand_kind() {
+        assert_eq!(OperandKind::of("rax"), OperandKind::Register);
+        assert_eq!(OperandKind::of("%r11d"), OperandKind::Register);
+        assert_eq!(OperandKind::of("*%rax"), OperandKind::Register);
+        assert_eq!(OperandKind::of("xmm3"), OperandKind::Register);
+        assert_eq!(
+            OperandKind::of("QWORD PTR [rip+0x2ef7]"),
+            OperandKind::Memory
+        );
+        assert_eq!(OperandKind::of("*0x8(%rax)"), OperandKind::Memory);
+        assert_eq!(OperandKind::of("0x8(rsp,rax,8)"), OperandKind::Memory);
+        assert_eq!(OperandKind::of("(,%rax,4)"), OperandKind::Memory);
+        assert_eq!(OperandKind::of("0x10"), OperandKind::Other);
+        assert_eq!(OperandKind::of("1234 <foo>"), OperandKind::Other);
+        assert_eq!(
+            OperandKind::of("core::ptr::drop_in_place<(u8,u16)>"),
+            OperandKind::Other
+        );
+    }
+
+    #[test]
+    fn test_objdump_option() {
+        assert_eq!(Syntax::Intel.objdump_option(), Some("-Mintel"));
+        assert_eq!(Syntax::Att.objdump_option(), Some("-Matt"));
+        assert_eq!(Syntax::Other.objdump_option(), None);
+        assert_eq!(Syntax::from_name("none"), Some(Syntax::Other));
+    }
+
+    #[test]
+    fn test_same_as() {
+        let intel =
+            |content: &str| instruction(content.split(' ').next().unwrap(), content, Syntax::Intel);
+        let call = intel("call   QWORD PTR [rip+0x2ee2]        # 17ff8 <memcpy@GLIBC_2.14>");
+
+        assert!(call.same_as(&intel(
+            "call   QWORD PTR [rip+0x2ee2]        # 17ff8 <memcpy@GLIBC_2.14>"
+        )));
+        // a different target, only visible in the comment
+        assert!(!call.same_as(&intel(
+            "call   QWORD PTR [rip+0x2ee2]        # 17ff8 <memmove@GLIBC_2.2.5>"
+        )));
+        assert!(!intel("call   14728 <a::helper>").same_as(&intel("call   14728 <a::other>")));
+        assert!(intel("mov    eax,0x1").same_as(&instruction(
+            "movl",
+            "movl   $0x1,%eax",
+            Syntax::Att
+        )));
+
+        // memory operands, with objdump's and assemblers' spellings of both
+        // syntaxes
+        let att = |op: &str, content: &str| instruction(op, content, Syntax::Att);
+        let load = intel("mov    rax,QWORD PTR [rsp+rax*1+0x8]");
+        assert!(load.same_as(&att("mov", "mov    0x8(%rsp,%rax,1),%rax")));
+        assert!(load.same_as(&att("movq", "movq\t8(%rsp,%rax), %rax")));
+        assert!(load.same_as(&intel("mov rax, qword ptr [rsp + 8 + rax]")));
+        assert!(!load.same_as(&att("mov", "mov    0x8(%rsp,%rax,2),%rax")));
+        assert!(!load.same_as(&att("mov", "mov    0x10(%rsp,%rax,1),%rax")));
+        assert!(intel("lea    rcx,[rax*8+0x0]").same_as(&att("lea", "lea    0x0(,%rax,8),%rcx")));
+        assert!(intel("mov    rdi,QWORD PTR [rbp-0x18]")
+            .same_as(&att("mov", "mov    -0x18(%rbp),%rdi")));
+        assert!(intel("call   QWORD PTR [rax+0x8]").same_as(&att("call", "call   *0x8(%rax)")));
+        assert!(intel("call   rax").same_as(&att("call", "call   *%rax")));
+        assert!(intel("mov    rax,QWORD PTR fs:0x28").same_as(&att("mov", "mov    %fs:0x28,%rax")));
+    }
+
+    #[test]
+    fn test_demangle_symbol() {
+        assert_eq!(
+            demangle_symbol("_ZN4core3fmt5write17h0123456789abcdefE"),
+            "core::fmt::write::h0123456789abcdef"
+        );
+        assert_eq!(
+            demangle_symbol("_RNvCs1234_7mycrate3foo"),
+            "mycrate[3c1c0]::foo"
+        );
+        assert_eq!(demangle_symbol("_Z3fooi"), "foo(int)");
+        assert_eq!(
+            demangle_symbol("_ZNSt6vectorIiSaIiEE9push_backEOi"),
+            "std::vector<int, std::allocator<int> >::push_back(int&&)"
+        );
+        assert_eq!(demangle_symbol("_Z3foov.cold"), "foo().cold");
+        assert_eq!(demangle_symbol("_Zinvalid"), "_Zinvalid");
+        assert_eq!(demangle_symbol("_Rust"), "_Rust");
+    }
+
+    // (raw text, demangled, demangled without hashes), from real binaries
+    const SYMBOL_CORPUS: &[(&str, &str, &str)] = &[
+        (
+            "_ZN4core3ptr65drop_in_place$LT$alloc..vec..Vec$LT$alloc..string..String$GT$$GT$17h3381e8edcb335bfcE",
+            "core::ptr::drop_in_place<alloc::vec::Vec<alloc::string::String>>::h3381e8edcb335bfc",
+            "core::ptr::drop_in_place<alloc::vec::Vec<alloc::string::String>>",
+        ),
+        (
+            "_ZN5alloc7raw_vec19RawVec$LT$T$C$A$GT$8grow_one17h1212d1987e1f53dbE",
+            "alloc::raw_vec::RawVec<T,A>::grow_one::h1212d1987e1f53db",
+            "alloc::raw_vec::RawVec<T,A>::grow_one",
+        ),
+        (
+            "_ZN3std2rt10lang_start28_$u7b$$u7b$closure$u7d$$u7d$17he39bdf0230cb3880E",
+            "std::rt::lang_start::{{closure}}::he39bdf0230cb3880",
+            "std::rt::lang_start::{{closure}}",
+        ),
+        (
+            "_ZN102_$LT$core..iter..adapters..map..Map$LT$I$C$F$GT$$u20$as$u20$core..iter..traits..iterator..Iterator$GT$4fold17h0123456789abcdefE",
+            "<core::iter::adapters::map::Map<I,F> as core::iter::traits::iterator::Iterator>::fold::h0123456789abcdef",
+            "<core::iter::adapters::map::Map<I,F> as core::iter::traits::iterator::Iterator>::fold",
+        ),
+        (
+            "_ZN4core3fmt5write17h0123456789abcdefE.llvm.4237926541958934961",
+            "core::fmt::write::h0123456789abcdef",
+            "core::fmt::write",
+        ),
+        (
+            "_ZN4core9panicking5panic17h0123456789abcdefE+0x1a",
+            "core::panicking::panic::h0123456789abcdef+0x1a",
+            "core::panicking::panic+0x1a",
+        ),
+        (
+            "_RINvCsfq2Zq5gqAK1_2v01gThAtj3_EEB2_",
+            "v0[b39b9a665996db47]::g::<(u8, [u16; 3usize])>",
+            "v0[b39b9a665996db47]::g::<(u8, [u16; 3usize])>",
+        ),
+        (
+            "_RINvMs4_NtCsgY6Mt91CT9J_14rustc_demangle2v0NtB6_7Printer13print_backrefNCNvB2_10print_paths_0EB8_",
+            "<rustc_demangle[c5a78273c295e539]::v0::Printer>::print_backref::<<rustc_demangle[c5a78273c295e539]::v0::Printer>::print_path::{closure#1}>",
+            "<rustc_demangle[c5a78273c295e539]::v0::Printer>::print_backref::<<rustc_demangle[c5a78273c295e539]::v0::Printer>::print_path::{closure#1}>",
+        ),
+        (
+            "_Z1fRSt6vectorIiSaIiEENSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEEE",
+            "f(std::vector<int, std::allocator<int> >&, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >)",
+            "f(std::vector<int, std::allocator<int> >&, std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >)",
+        ),
+        (
+            "_ZNSt12_Vector_baseIiSaIiEED1Ev",
+            "std::_Vector_base<int, std::allocator<int> >::~_Vector_base()",
+            "std::_Vector_base<int, std::allocator<int> >::~_Vector_base()",
+        ),
+        (
+            "_ZSt20__throw_length_errorPKc@plt",
+            "std::__throw_length_error(char const*)@plt",
+            "std::__throw_length_error(char const*)@plt",
+        ),
+        ("__Z3foov", "foo()", "foo()"),
+        ("memcpy@GLIBC_2.14", "memcpy@GLIBC_2.14", "memcpy@GLIBC_2.14"),
+        (
+            "cmp    rax, QWORD PTR [rip+0x10]        # 4000 <_ZN1a1x17h0123456789abcdefE> <_ZN1a1y17hfedcba9876543210E>",
+            "cmp    rax, QWORD PTR [rip+0x10]        # 4000 <a::x::h0123456789abcdef> <a::y::hfedcba9876543210>",
+            "cmp    rax, QWORD PTR [rip+0x10]        # 4000 <a::x> <a::y>",
+        ),
+    ];
+
+    #[test]
+    fn test_demangle_all() {
+        for (raw, demangled, without_hash) in SYMBOL_CORPUS {
+            assert_eq!(demangle_all(raw, false), *demangled);
+            assert_eq!(demangle_all(raw, true), *without_hash);
+        }
+    }
+
+    #[test]
+    fn test_att_mnemonic() {
+        assert_eq!(att_mnemonic("movq", "$0x0,0x8(%rsp)"), "mov");
+        assert_eq!(att_mnemonic("movq", "%xmm0,%rax"), "movq");
+        assert_eq!(att_mnemonic("callq", "14700 <foo>"), "call");
+        assert_eq!(att_mnemonic("call", "14700 <foo>"), "call");
+        assert_eq!(att_mnemonic("shl", "%cl,%eax"), "shl");
+        assert_eq!(att_mnemonic("shll", "%cl,%eax"), "shl");
+        assert_eq!(att_mnemonic("cmovl", "%ecx,%eax"), "cmovl");
+        assert_eq!(att_mnemonic("cmovlq", "%rcx,%rax"), "cmovl");
+        assert_eq!(att_mnemonic("movzbl", "(%rdi),%eax"), "movzx");
+        assert_eq!(att_mnemonic("movsbq", "(%rdi),%rax"), "movsx");
+        assert_eq!(att_mnemonic("movslq", "(%rdi),%rax"), "movsxd");
+        assert_eq!(att_mnemonic("movsq", "%ds:(%rsi),%es:(%rdi)"), "movs");
+        assert_eq!(att_mnemonic("cltq", ""), "cdqe");
+        assert_eq!(att_mnemonic("setl", "%al"), "setl");
+        assert_eq!(att_mnemonic("jl", "1234 <foo+0x4>"), "jl");
+    }
+
+    #[test]
+    fn test_split_operands() {
+        assert_eq!(
+            split_operands("0x8(%rsp,%rax,1),%rdi"),
+            vec!["0x8(%rsp,%rax,1)", "%rdi"]
+        );
+        assert_eq!(
+            split_operands("rdi,QWORD PTR [rsp+0x8]"),
+            vec!["rdi", "QWORD PTR [rsp+0x8]"]
+        );
+        assert_eq!(split_operands("1234 <foo<A, B>>"), vec!["1234 <foo<A, B>>"]);
+        assert!(split_operands("").is_empty());
+    }
+}
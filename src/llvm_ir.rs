@@ -0,0 +1,171 @@
+use crate::gas::strip_comment;
+use crate::line::{demangle_all, Syntax};
+use crate::AsmFile;
+use crate::Line;
+use crate::CFG;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static RE_DEFINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^define .*?@("(?:[^"\\]|\\.)*"|[-a-zA-Z$._0-9]+)\("#).expect("bug: wrong regex")
+});
+
+static RE_BB_LABEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^("(?:[^"\\]|\\.)*"|[-a-zA-Z$._0-9]+):"#).expect("bug: wrong regex")
+});
+
+static RE_DBG: Lazy<Regex> = Lazy::new(|| Regex::new(r", !dbg ![0-9]+").expect("bug: wrong regex"));
+
+// markers that can precede the opcode of a call
+const CALL_MARKERS: &[&str] = &["tail", "musttail", "notail"];
+
+fn unquote(name: &str) -> &str {
+    name.strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+        .unwrap_or(name)
+}
+
+fn is_debug_instruction(instruction: &str) -> bool {
+    instruction.starts_with("#dbg_") || instruction.contains("@llvm.dbg.")
+}
+
+/// Splits an instruction into opcode and operands. The name of the result
+/// (`%x = `) is left out, since values are renumbered between builds.
+fn split_instruction(instruction: &str) -> (&str, &str) {
+    let mut rest = match instruction.split_once(" = ") {
+        Some((result, rest)) if result.starts_with('%') => rest,
+        _ => instruction,
+    };
+
+    loop {
+        let (op, operands) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        if CALL_MARKERS.contains(&op) {
+            rest = operands;
+        } else {
+            return (op, operands);
+        }
+    }
+}
+
+fn push_instruction(asm: &mut AsmFile, instruction: &str, strip_debug: bool) {
+    if strip_debug && is_debug_instruction(instruction) {
+        return;
+    } else if instruction.starts_with('#') {
+        // debug records are not instructions
+        asm.push(Line::Other(instruction.to_owned()));
+        return;
+    }
+
+    let instruction = if strip_debug {
+        RE_DBG.replace_all(instruction, "")
+    } else {
+        instruction.into()
+    };
+    let (op, operands) = split_instruction(&instruction);
+    let content = match operands {
+        "" => String::new(),
+        operands => format!(
+            "{:padding$} {}",
+            "",
+            operands,
+            padding = 6_usize.saturating_sub(op.len())
+        ),
+    };
+
+    asm.push(Line::instruction(
+        op,
+        "",
+        "",
+        &content,
+        &instruction,
+        Syntax::Other,
+    ));
+}
+
+/// Reads textual LLVM IR (e.g. `rustc --emit llvm-ir`). Each function
+/// definition becomes a block of a `.text` section, with basic block labels
+/// as markers inside it. Everything outside function bodies is skipped.
+pub fn read_llvm_ir_from_str(text: &str, strip_debug: bool) -> AsmFile {
+    let mut asm = AsmFile::new();
+    let mut in_function = false;
+    // instructions can span several lines (e.g. `invoke`, `switch`)
+    let mut pending = String::new();
+
+    asm.push(Line::section_header(".text"));
+
+    for raw_line in text.split('\n') {
+        let line = strip_comment(raw_line, ';').trim_end();
+
+        if !in_function {
+            if let Some(caps) = RE_DEFINE.captures(line) {
+                asm.push(Line::label(unquote(&caps[1]), ""));
+                in_function = true;
+            }
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim_start();
+        let open_brackets = pending.matches('[').count() > pending.matches(']').count();
+        let continues = !pending.is_empty()
+            && !line.is_empty()
+            && !line.starts_with("#dbg_")
+            && (open_brackets || indent > 2);
+
+        if continues {
+            pending.push(' ');
+            pending.push_str(line);
+            continue;
+        }
+
+        if !pending.is_empty() {
+            push_instruction(&mut asm, &pending, strip_debug);
+            pending.clear();
+        }
+
+        if line == "}" {
+            asm.push(Line::Blank);
+            in_function = false;
+        } else if line.is_empty() {
+            asm.push(Line::Blank);
+        } else if indent == 0 {
+            if let Some(caps) = RE_BB_LABEL.captures(line) {
+                // demangled like the branches to it
+                let label = demangle_all(unquote(&caps[1]), CFG.no_hash);
+                asm.push(Line::LocalLabel(label.into_owned()));
+            }
+        } else {
+            pending.push_str(line);
+        }
+    }
+
+    asm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_instruction() {
+        assert_eq!(
+            split_instruction("%3 = icmp eq i64 %v.val, 0"),
+            ("icmp", "eq i64 %v.val, 0")
+        );
+        assert_eq!(
+            split_instruction("store i64 0, ptr %v, align 8"),
+            ("store", "i64 0, ptr %v, align 8")
+        );
+        assert_eq!(
+            split_instruction("tail call void @foo(ptr %v)"),
+            ("call", "void @foo(ptr %v)")
+        );
+        assert_eq!(
+            split_instruction("%r = musttail call i32 @f()"),
+            ("call", "i32 @f()")
+        );
+        assert_eq!(split_instruction("ret void"), ("ret", "void"));
+        assert_eq!(split_instruction("unreachable"), ("unreachable", ""));
+    }
+}
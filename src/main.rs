@@ -1,116 +1,459 @@
 mod asm_file;
+mod call_graph;
 mod config;
+mod control_flow;
+mod cost;
 mod create_asm;
 mod diff;
+mod gas;
+mod histogram;
 mod line;
+mod llvm_ir;
+mod matching;
+mod normalize;
 mod read_asm;
+mod size_diff;
+mod symbol;
+mod wasm;
 
 pub use asm_file::AsmFile;
 pub use config::CFG;
 pub use line::Line;
+use matching::MatchKind;
+use std::borrow::Borrow;
+use std::collections::HashSet;
 
-fn line_diff() {
-    let section = CFG.section.as_ref().expect("need to supply --section");
-    let left = CFG.left_ix.expect("need to supply --left-ix");
-    let right = CFG.right_ix.expect("need to supply --right-ix");
+/// Reads the two files to diff, or `None` if either can't be read.
+fn read_asm_files() -> Option<(AsmFile, AsmFile)> {
     let right_file = CFG
         .right_file
         .as_ref()
         .expect("must provide second file name");
 
-    let left_asm = create_asm::create_asm_for_arg(&CFG.left_file);
-    let right_asm = create_asm::create_asm_for_arg(right_file);
+    let left_asm = read_asm::read_asm_for_arg(&CFG.left_file);
+    let right_asm = read_asm::read_asm_for_arg(right_file);
+
+    left_asm.zip(right_asm)
+}
 
-    if let (Some(left_asm), Some(right_asm)) = (left_asm, right_asm) {
-        let asm1 = read_asm::read_asm_from_memory(left_asm);
-        let asm2 = read_asm::read_asm_from_memory(right_asm);
+/// The section of both files with the name, or `None` (with a message) if
+/// either file doesn't have it.
+fn both_sections<'a>(
+    asm1: &'a AsmFile,
+    asm2: &'a AsmFile,
+    section: &str,
+) -> Option<(&'a asm_file::Section, &'a asm_file::Section)> {
+    let right_file = CFG.right_file.as_deref().unwrap_or_default();
+
+    match (asm1.get_section(section), asm2.get_section(section)) {
+        (Some(section1), Some(section2)) => Some((section1, section2)),
+        (None, _) => {
+            println!("no section {} in {}", section, CFG.left_file);
+            None
+        }
+        (_, None) => {
+            println!("no section {} in {}", section, right_file);
+            None
+        }
+    }
+}
 
-        let text1 = asm1.get_section(section).unwrap();
-        let text2 = asm2.get_section(section).unwrap();
+fn line_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    let left = CFG.left_ix.expect("need to supply --left-ix");
+    let right = CFG.right_ix.expect("need to supply --right-ix");
+
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
+
+        let block1 = &text1.blocks()[left];
+        let block2 = &text2.blocks()[right];
+
+        if CFG.only_loops {
+            let graph1 = control_flow::ControlFlowGraph::new(block1);
+            let graph2 = control_flow::ControlFlowGraph::new(block2);
+            print_line_diff(&graph1.loop_lines(), &graph2.loop_lines());
+        } else {
+            print_line_diff(block1.lines(), block2.lines());
+        }
+    }
+}
 
-        let lines1 = text1.blocks()[left].lines();
-        let lines2 = text2.blocks()[right].lines();
-        let diff = diff::diff(lines1, lines2, |a, b| match (a, b) {
-            (Line::Instruction(i), Line::Instruction(j)) => i.op() == j.op(),
+fn print_line_diff<L: Borrow<Line>>(lines1: &[L], lines2: &[L]) {
+    let diff = diff::diff(lines1, lines2, |a, b| match (a.borrow(), b.borrow()) {
+        (Line::Instruction(i), Line::Instruction(j)) => i.mnemonic() == j.mnemonic(),
+        (Line::LocalLabel(_), Line::LocalLabel(_)) => true,
+        (a, b) => a == b,
+    });
+
+    diff::print_alignment(
+        lines1,
+        lines2,
+        diff,
+        |line| match line.borrow() {
+            Line::Instruction(i) if CFG.show_bytes => {
+                format!("{:<30} {}", i.bytes_str(), i.content()).into()
+            }
+            line => line.as_str().into(),
+        },
+        |l1, l2| match (l1.borrow(), l2.borrow()) {
+            (Line::Instruction(i), Line::Instruction(j)) => i.same_as(j),
             (a, b) => a == b,
+        },
+    );
+}
+
+/// Diffs two versions of a function basic block by basic block, so that
+/// moved basic blocks show up as moves rather than as deletions and
+/// insertions.
+fn cfg_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
+
+        let block1 = selected_block(text1, CFG.left_ix);
+        let block2 = selected_block(text2, CFG.right_ix);
+        let graph1 = control_flow::ControlFlowGraph::new(block1);
+        let graph2 = control_flow::ControlFlowGraph::new(block2);
+        let matches =
+            control_flow::match_basic_blocks(&graph1, &graph2, CFG.min_similarity / 100.0);
+        let moved = control_flow::reordered(&matches);
+
+        let mut identical = 0;
+        for m in &matches {
+            let lines1 = graph1.lines(m.left);
+            let lines2 = graph2.lines(m.right);
+            let same = normalize::normalized_instructions(lines1, block1.demangled_label())
+                == normalize::normalized_instructions(lines2, block2.demangled_label());
+            let status = match (same, moved.contains(&m.left)) {
+                (true, false) => "identical",
+                (true, true) => "moved",
+                (false, false) => "changed",
+                (false, true) => "moved, changed",
+            };
+
+            if same {
+                identical += 1;
+                if CFG.only_diff && status == "identical" {
+                    continue;
+                }
+            }
+            if CFG.show_cost {
+                let cost1 = graph1.basic_block_cost(m.left);
+                let cost2 = graph2.basic_block_cost(m.right);
+                println!(
+                    "bb{} -> bb{} ({}, cost {:.1} -> {:.1}, {:+.1})",
+                    m.left,
+                    m.right,
+                    status,
+                    cost1,
+                    cost2,
+                    cost2 - cost1
+                );
+            } else {
+                println!("bb{} -> bb{} ({})", m.left, m.right, status);
+            }
+            if !same {
+                print_line_diff(lines1, lines2);
+            }
+        }
+
+        let matched1: HashSet<usize> = matches.iter().map(|m| m.left).collect();
+        let matched2: HashSet<usize> = matches.iter().map(|m| m.right).collect();
+        let removed: Vec<usize> = (0..graph1.basic_blocks().len())
+            .filter(|bb| !matched1.contains(bb))
+            .collect();
+        let added: Vec<usize> = (0..graph2.basic_blocks().len())
+            .filter(|bb| !matched2.contains(bb))
+            .collect();
+
+        for &bb in &removed {
+            println!("bb{} removed", bb);
+            print_line_diff(graph1.lines(bb), &[]);
+        }
+        for &bb in &added {
+            println!("bb{} added", bb);
+            print_line_diff(&[], graph2.lines(bb));
+        }
+
+        println!();
+        println!(
+            "{} basic blocks matched ({} identical), {} moved, {} removed, {} added",
+            matches.len(),
+            identical,
+            moved.len(),
+            removed.len(),
+            added.len()
+        );
+        if CFG.show_cost {
+            let cost1 = graph1.cost();
+            let cost2 = graph2.cost();
+            println!(
+                "function cost {:.1} -> {:.1} ({:+.1} {} cycles)",
+                cost1,
+                cost2,
+                cost2 - cost1,
+                CFG.uarch
+            );
+        }
+    }
+}
+
+/// Aligns blocks by name, then pairs the blocks left over on both sides by
+/// the similarity of their contents (unless `--no-content-match`).
+fn align_blocks(
+    section1: &asm_file::Section,
+    section2: &asm_file::Section,
+) -> (diff::Alignment, Vec<matching::Match>) {
+    let blocks1 = section1.blocks();
+    let blocks2 = section2.blocks();
+    let mut alignment = diff::align(blocks1, blocks2, |bl1, bl2| {
+        bl1.demangled_label() == bl2.demangled_label()
+    });
+    let mut matches = Vec::new();
+    let threshold = CFG.min_similarity / 100.0;
+
+    if let Some(filename) = &CFG.rename_map {
+        let renames = matching::read_rename_map(filename);
+        let found = matching::match_leftovers(&alignment, 1.0, MatchKind::RenameMap, |l, r| {
+            let renamed = renames.get(symbol::strip_hash(blocks1[l].demangled_label()));
+            if renamed.is_some_and(|name| name == symbol::strip_hash(blocks2[r].demangled_label()))
+            {
+                1.0
+            } else {
+                0.0
+            }
         });
+        alignment = matching::apply_matches(alignment, &found);
+        matches.extend(found);
+    }
 
-        diff::print_alignment(
-            &lines1,
-            &lines2,
-            diff,
-            |line| line.as_str(),
-            |l1, l2| match (l1, l2) {
-                (Line::Instruction(i), Line::Instruction(j)) => i.content() == j.content(),
-                (a, b) => a == b,
-            },
+    match CFG.name_match.as_str() {
+        "exact" => (),
+        "fuzzy" => {
+            let found =
+                matching::match_leftovers(&alignment, threshold, MatchKind::Name, |l, r| {
+                    symbol::name_similarity(
+                        blocks1[l].demangled_label(),
+                        blocks2[r].demangled_label(),
+                    )
+                });
+            alignment = matching::apply_matches(alignment, &found);
+            matches.extend(found);
+        }
+        _ => panic!("unknown --name-match"),
+    }
+
+    if !CFG.no_content_match {
+        let similarity = matching::ContentSimilarity::new(blocks1, blocks2, &alignment);
+        let found = matching::match_leftovers(&alignment, threshold, MatchKind::Content, |l, r| {
+            similarity.score(l, r)
+        });
+        alignment = matching::apply_matches(alignment, &found);
+        matches.extend(found);
+    }
+
+    (alignment, matches)
+}
+
+fn print_matches(
+    blocks1: &[asm_file::Block],
+    blocks2: &[asm_file::Block],
+    matches: &[matching::Match],
+) {
+    if matches.is_empty() {
+        return;
+    }
+
+    println!();
+    println!("renamed/moved blocks:");
+    for m in matches {
+        println!(
+            "  {:<7} {:>5.1}% {} -> {}",
+            m.kind.name(),
+            m.score * 100.0,
+            CFG.name_policy().apply(blocks1[m.left].demangled_label()),
+            CFG.name_policy().apply(blocks2[m.right].demangled_label())
         );
     }
 }
 
-fn block_diff() {
-    let section = CFG.section.as_ref().expect("need to supply --section");
-    let right_file = CFG
-        .right_file
-        .as_ref()
-        .expect("must provide second file name");
+fn print_size_changes(
+    blocks1: &[asm_file::Block],
+    blocks2: &[asm_file::Block],
+    alignment: &[(Option<usize>, Option<usize>)],
+) {
+    let mut total = 0;
+
+    println!("size changes (bytes):");
+    for (l, r) in alignment {
+        let size1 = l.map_or(0, |l| blocks1[l].byte_size() as i64);
+        let size2 = r.map_or(0, |r| blocks2[r].byte_size() as i64);
+        let block = match (l, r) {
+            (_, Some(r)) => &blocks2[*r],
+            (Some(l), None) => &blocks1[*l],
+            (None, None) => continue,
+        };
 
-    let left_asm = create_asm::create_asm_for_arg(&CFG.left_file);
-    let right_asm = create_asm::create_asm_for_arg(right_file);
+        if size1 != size2 {
+            println!(
+                "  {:>+7} {:>7} -> {:<7} {}",
+                size2 - size1,
+                size1,
+                size2,
+                CFG.name_policy().apply(block.demangled_label())
+            );
+            total += size2 - size1;
+        }
+    }
+    println!("  {:>+7} total", total);
+    println!();
+}
 
-    if let (Some(left_asm), Some(right_asm)) = (left_asm, right_asm) {
-        let asm1 = read_asm::read_asm_from_memory(left_asm);
-        let asm2 = read_asm::read_asm_from_memory(right_asm);
+fn print_cost_changes(
+    blocks1: &[asm_file::Block],
+    blocks2: &[asm_file::Block],
+    alignment: &[(Option<usize>, Option<usize>)],
+) {
+    let cost = |block: &asm_file::Block| control_flow::ControlFlowGraph::new(block).cost();
+    let mut total = 0.0;
 
-        let text1 = asm1.get_section(section).unwrap();
-        let text2 = asm2.get_section(section).unwrap();
+    println!("cost changes ({} cycles):", CFG.uarch);
+    for (l, r) in alignment {
+        let cost1 = l.map_or(0.0, |l| cost(&blocks1[l]));
+        let cost2 = r.map_or(0.0, |r| cost(&blocks2[r]));
+        let block = match (l, r) {
+            (_, Some(r)) => &blocks2[*r],
+            (Some(l), None) => &blocks1[*l],
+            (None, None) => continue,
+        };
 
-        let alignment = diff::align(&text1.blocks(), &text2.blocks(), |bl1, bl2| {
-            bl1.demangled_label() == bl2.demangled_label()
-        });
+        if (cost2 - cost1).abs() >= 0.05 {
+            println!(
+                "  {:>+9.1} {:>9.1} -> {:<9.1} {}",
+                cost2 - cost1,
+                cost1,
+                cost2,
+                CFG.name_policy().apply(block.demangled_label())
+            );
+            total += cost2 - cost1;
+        }
+    }
+    println!("  {:>+9.1} total", total);
+    println!();
+}
+
+fn block_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
+
+        let (alignment, matches) = align_blocks(text1, text2);
+
+        if CFG.show_bytes {
+            print_size_changes(text1.blocks(), text2.blocks(), &alignment);
+        }
+        if CFG.show_cost {
+            print_cost_changes(text1.blocks(), text2.blocks(), &alignment);
+        }
 
         diff::print_alignment(
-            &text1.blocks(),
-            &text2.blocks(),
+            text1.blocks(),
+            text2.blocks(),
             alignment,
-            |block| block.demangled_label(),
+            |block| CFG.name_policy().apply(block.demangled_label()),
             |bl1, bl2| bl1.label() == bl2.label(),
         );
+        print_matches(text1.blocks(), text2.blocks(), &matches);
     }
 }
 
-fn section_diff() {
-    let right_file = CFG
-        .right_file
-        .as_ref()
-        .expect("must provide second file name");
+fn size_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
 
-    let left_asm = create_asm::create_asm_for_arg(&CFG.left_file);
-    let right_asm = create_asm::create_asm_for_arg(right_file);
+        let (alignment, _) = align_blocks(text1, text2);
+        let rows = size_diff::size_rows(text1.blocks(), text2.blocks(), &alignment);
 
-    if let (Some(left_asm), Some(right_asm)) = (left_asm, right_asm) {
-        let asm1 = read_asm::read_asm_from_memory(left_asm);
-        let asm2 = read_asm::read_asm_from_memory(right_asm);
+        match CFG.depth {
+            Some(depth) => size_diff::print_size_diff(size_diff::group_rows(rows, depth)),
+            None => size_diff::print_size_diff(rows),
+        }
+    }
+}
 
-        let alignment = diff::align(&asm1.sections(), &asm2.sections(), |sec_a, sec_b| {
+fn section_diff() {
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let alignment = diff::align(asm1.sections(), asm2.sections(), |sec_a, sec_b| {
             sec_a.name() == sec_b.name()
         });
 
         diff::print_alignment(
-            &asm1.sections(),
-            &asm2.sections(),
+            asm1.sections(),
+            asm2.sections(),
             alignment,
-            |sec| sec.name(),
+            |sec| sec.name().into(),
             |s1, s2| s1.name() == s2.name(),
         );
     }
 }
 
+/// The mnemonic histogram of the block at `ix` in the section of `--section`,
+/// of that section, or of the whole file, with a description of what it
+/// covers.
+fn histogram_for(asm: &AsmFile, ix: Option<usize>) -> (histogram::Histogram, String) {
+    match &CFG.section {
+        Some(section_name) => {
+            let section = asm
+                .get_section(section_name)
+                .expect("no section with provided name");
+
+            match ix {
+                Some(ix) => {
+                    let block = &section.blocks()[ix];
+                    let name = CFG
+                        .name_policy()
+                        .apply(block.demangled_label())
+                        .into_owned();
+                    (histogram::Histogram::of_blocks([block]), name)
+                }
+                None => (
+                    histogram::Histogram::of_blocks(section.blocks()),
+                    format!("section {}", section.name()),
+                ),
+            }
+        }
+        None => (
+            histogram::Histogram::of_blocks(asm.sections().iter().flat_map(|s| s.blocks())),
+            "all sections".to_owned(),
+        ),
+    }
+}
+
+fn mnemonic_diff() {
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let (histogram1, scope1) = histogram_for(&asm1, CFG.left_ix);
+        let (histogram2, scope2) = histogram_for(&asm2, CFG.right_ix.or(CFG.left_ix));
+
+        println!("mnemonics of {} -> {}:", scope1, scope2);
+        histogram1.print_diff(&histogram2);
+    }
+}
+
 fn disassemble() {
-    let left_asm = create_asm::create_asm_for_arg(&CFG.left_file);
+    let left_asm = read_asm::disassembly_for_arg(&CFG.left_file);
 
     if let Some(left_asm) = left_asm {
-        println!("{}", String::from_utf8_lossy(&left_asm.stdout));
+        println!("{}", left_asm);
     }
 }
 
@@ -119,11 +462,9 @@ fn summary() {
         .summary_type
         .as_ref()
         .expect("need to supply --summary-type");
-    let left_asm = create_asm::create_asm_for_arg(&CFG.left_file);
-
-    if let Some(left_asm) = left_asm {
-        let asm = read_asm::read_asm_from_memory(left_asm);
+    let left_asm = read_asm::read_asm_for_arg(&CFG.left_file);
 
+    if let Some(asm) = left_asm {
         match typ.as_ref() {
             "global" => asm.print_stats(),
             "sections" => asm.print_section_stats(),
@@ -134,19 +475,167 @@ fn summary() {
                     .expect("no section with provided name");
                 section.print_block_summary();
             }
+            "groups" => {
+                let section_name = CFG.section.as_ref().expect("must provide --section");
+                let section = asm
+                    .get_section(section_name)
+                    .expect("no section with provided name");
+                section.print_group_summary(CFG.depth.unwrap_or(1));
+            }
+            "generics" => {
+                let section_name = CFG.section.as_ref().expect("must provide --section");
+                let section = asm
+                    .get_section(section_name)
+                    .expect("no section with provided name");
+                section.print_generics_summary();
+            }
+            "loops" => {
+                let section_name = CFG.section.as_ref().expect("must provide --section");
+                let section = asm
+                    .get_section(section_name)
+                    .expect("no section with provided name");
+                control_flow::print_loop_summary(section);
+            }
+            "cost" => {
+                let section_name = CFG.section.as_ref().expect("must provide --section");
+                let section = asm
+                    .get_section(section_name)
+                    .expect("no section with provided name");
+                control_flow::print_cost_summary(section);
+            }
+            "mnemonics" => {
+                let (histogram, scope) = histogram_for(&asm, CFG.left_ix);
+                histogram.print(&scope);
+            }
+            "identical" => {
+                let section_name = CFG.section.as_ref().expect("must provide --section");
+                let section = asm
+                    .get_section(section_name)
+                    .expect("no section with provided name");
+                section.print_identical_code_summary();
+            }
+            "address" => {
+                let address = CFG.address.as_ref().expect("must provide --address");
+                let address = u64::from_str_radix(address.trim_start_matches("0x"), 16)
+                    .expect("--address must be hexadecimal");
+
+                match asm.block_at(address) {
+                    Some((section, block)) => {
+                        print!("section {}: ", section.name());
+                        block.print_address_range();
+                    }
+                    None => println!("no block contains address {:x}", address),
+                }
+            }
             _ => panic!("unknown --summary-type"),
         }
     }
 }
 
+fn call_graph() {
+    let left_asm = read_asm::read_asm_for_arg(&CFG.left_file);
+
+    if let Some(asm) = left_asm {
+        let graph = call_graph::CallGraph::new(&asm);
+
+        if let Some(symbol) = &CFG.symbol {
+            match graph.find(symbol) {
+                Some(node) => graph.print_neighbours(node),
+                None => println!("no block named {}", symbol),
+            }
+            return;
+        }
+
+        match CFG.graph_format.as_str() {
+            "text" => graph.print_text(),
+            "dot" => graph.print_dot(),
+            "json" => graph.print_json(),
+            _ => panic!("unknown --graph-format"),
+        }
+    }
+}
+
+fn call_graph_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
+
+        let (alignment, _) = align_blocks(text1, text2);
+        let graph1 = call_graph::CallGraph::new(&asm1);
+        let graph2 = call_graph::CallGraph::new(&asm2);
+
+        call_graph::print_call_diff(&graph1, &graph2, section, &alignment);
+    }
+}
+
+fn inlining_diff() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    if let Some((asm1, asm2)) = read_asm_files() {
+        let Some((text1, text2)) = both_sections(&asm1, &asm2, section) else {
+            return;
+        };
+
+        let (alignment, _) = align_blocks(text1, text2);
+        let graph1 = call_graph::CallGraph::new(&asm1);
+        let graph2 = call_graph::CallGraph::new(&asm2);
+
+        call_graph::print_inlining_changes(&graph1, &graph2, section, &alignment);
+    }
+}
+
+/// The block named by `--symbol` (with or without its hash), or else the one
+/// at `ix`.
+fn selected_block(section: &asm_file::Section, ix: Option<usize>) -> &asm_file::Block {
+    match &CFG.symbol {
+        Some(symbol) => section
+            .blocks()
+            .iter()
+            .find(|block| {
+                block.demangled_label() == symbol
+                    || symbol::strip_hash(block.demangled_label()) == symbol
+            })
+            .unwrap_or_else(|| panic!("no block named {}", symbol)),
+        None => &section.blocks()[ix.expect("need to supply --symbol or --left-ix/--right-ix")],
+    }
+}
+
+fn control_flow_graph() {
+    let section = CFG.section.as_ref().expect("need to supply --section");
+    let left_asm = read_asm::read_asm_for_arg(&CFG.left_file);
+
+    if let Some(asm) = left_asm {
+        let section = asm
+            .get_section(section)
+            .expect("no section with provided name");
+        let block = selected_block(section, CFG.left_ix);
+        let graph = control_flow::ControlFlowGraph::new(block);
+
+        match CFG.graph_format.as_str() {
+            "text" => graph.print_text(),
+            "dot" => graph.print_dot(),
+            "json" => graph.print_json(),
+            _ => panic!("unknown --graph-format"),
+        }
+    }
+}
+
 fn main() {
     match CFG.mode.as_str() {
         "summary" => summary(),
         "disassemble" => disassemble(),
+        "size-diff" => size_diff(),
+        "call-graph" => call_graph(),
+        "call-graph-diff" => call_graph_diff(),
+        "inlining" => inlining_diff(),
+        "cfg" => control_flow_graph(),
         "diff" => match CFG.level.as_deref() {
             Some("section") => section_diff(),
             Some("block") => block_diff(),
             Some("line") => line_diff(),
+            Some("cfg") => cfg_diff(),
+            Some("mnemonics") => mnemonic_diff(),
             None => panic!("must provide --level"),
             _ => panic!("unknown --level"),
         },
@@ -0,0 +1,231 @@
+use crate::asm_file::Block;
+use crate::diff::Alignment;
+use crate::normalize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// What a pair of blocks was matched by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    RenameMap,
+    Name,
+    Content,
+}
+
+impl MatchKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchKind::RenameMap => "map",
+            MatchKind::Name => "name",
+            MatchKind::Content => "content",
+        }
+    }
+}
+
+/// A pair of blocks matched by something other than equal names, with a
+/// score between 0 and 1.
+#[derive(Debug, Clone, Copy)]
+pub struct Match {
+    pub left: usize,
+    pub right: usize,
+    pub score: f64,
+    pub kind: MatchKind,
+}
+
+/// Reads a file of explicit renames, one `old => new` (or `old<TAB>new`) pair
+/// of demangled names per line. Blank lines and lines starting with `#` are
+/// skipped.
+pub fn read_rename_map(filename: &str) -> HashMap<String, String> {
+    let text = fs::read_to_string(filename)
+        .unwrap_or_else(|e| panic!("could not read rename map {}: {}", filename, e));
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (old, new) = line
+                .split_once(" => ")
+                .or_else(|| line.split_once('\t'))
+                .unwrap_or_else(|| panic!("invalid line in rename map: {}", line));
+            (old.trim().to_owned(), new.trim().to_owned())
+        })
+        .collect()
+}
+
+/// Pairs the elements an alignment left unmatched on both sides, best scores
+/// first, keeping only pairs that score at least `threshold`.
+pub fn match_leftovers<F>(
+    alignment: &[(Option<usize>, Option<usize>)],
+    threshold: f64,
+    kind: MatchKind,
+    score: F,
+) -> Vec<Match>
+where
+    F: Fn(usize, usize) -> f64,
+{
+    let left: Vec<usize> = alignment
+        .iter()
+        .filter_map(|pair| match pair {
+            (Some(l), None) => Some(*l),
+            _ => None,
+        })
+        .collect();
+    let right: Vec<usize> = alignment
+        .iter()
+        .filter_map(|pair| match pair {
+            (None, Some(r)) => Some(*r),
+            _ => None,
+        })
+        .collect();
+
+    let mut candidates = Vec::new();
+    for &l in &left {
+        for &r in &right {
+            let score = score(l, r);
+            if score >= threshold {
+                candidates.push(Match {
+                    left: l,
+                    right: r,
+                    score,
+                    kind,
+                });
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut left_used = HashSet::new();
+    let mut right_used = HashSet::new();
+    let mut matches = Vec::new();
+    for candidate in candidates {
+        if left_used.contains(&candidate.left) || right_used.contains(&candidate.right) {
+            continue;
+        }
+        left_used.insert(candidate.left);
+        right_used.insert(candidate.right);
+        matches.push(candidate);
+    }
+
+    matches
+}
+
+/// Puts the matched pairs into the alignment, where the left element was.
+pub fn apply_matches(alignment: Alignment, matches: &[Match]) -> Alignment {
+    let by_left: HashMap<usize, usize> = matches.iter().map(|m| (m.left, m.right)).collect();
+    let matched_right: HashSet<usize> = matches.iter().map(|m| m.right).collect();
+
+    alignment
+        .into_iter()
+        .filter_map(|pair| match pair {
+            (Some(l), None) => Some((Some(l), by_left.get(&l).copied())),
+            (None, Some(r)) if matched_right.contains(&r) => None,
+            pair => Some(pair),
+        })
+        .collect()
+}
+
+/// Blocks with fewer instructions are too common (a lone `ret`, empty drop
+/// glue) to be told apart by content, so they are never matched by it.
+const MIN_CONTENT_INSTRUCTIONS: usize = 4;
+
+/// Similarity of the normalized bodies of blocks: 1 if they are identical,
+/// otherwise the Dice coefficient of their multisets of instructions.
+pub struct ContentSimilarity {
+    left: HashMap<usize, (u64, HashMap<String, usize>, usize)>,
+    right: HashMap<usize, (u64, HashMap<String, usize>, usize)>,
+}
+
+impl ContentSimilarity {
+    /// Prepares the comparison of the blocks the alignment left unmatched
+    /// that have at least `MIN_CONTENT_INSTRUCTIONS` instructions.
+    pub fn new(
+        left: &[Block],
+        right: &[Block],
+        alignment: &[(Option<usize>, Option<usize>)],
+    ) -> Self {
+        let summarize = |block: &Block| {
+            let lines = normalize::normalized_lines(block);
+            let count = lines.len();
+            let mut histogram: HashMap<String, usize> = HashMap::new();
+
+            for line in lines {
+                *histogram.entry(line).or_default() += 1;
+            }
+            (normalize::block_hash(block), histogram, count)
+        };
+
+        Self {
+            left: alignment
+                .iter()
+                .filter_map(|pair| match pair {
+                    (Some(l), None) if left[*l].instruction_count() >= MIN_CONTENT_INSTRUCTIONS => {
+                        Some((*l, summarize(&left[*l])))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            right: alignment
+                .iter()
+                .filter_map(|pair| match pair {
+                    (None, Some(r))
+                        if right[*r].instruction_count() >= MIN_CONTENT_INSTRUCTIONS =>
+                    {
+                        Some((*r, summarize(&right[*r])))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn score(&self, left: usize, right: usize) -> f64 {
+        let (Some((hash1, histogram1, count1)), Some((hash2, histogram2, count2))) =
+            (self.left.get(&left), self.right.get(&right))
+        else {
+            return 0.0;
+        };
+
+        if *count1 == 0 || *count2 == 0 {
+            return 0.0;
+        } else if hash1 == hash2 {
+            return 1.0;
+        }
+
+        let common: usize = histogram1
+            .iter()
+            .map(|(line, n)| histogram2.get(line).map_or(0, |m| *n.min(m)))
+            .sum();
+
+        2.0 * common as f64 / (count1 + count2) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_leftovers() {
+        let alignment = vec![
+            (Some(0), Some(0)),
+            (Some(1), None),
+            (Some(2), None),
+            (None, Some(1)),
+            (None, Some(2)),
+        ];
+        let scores = [[0.0, 0.0, 0.0], [0.0, 0.5, 0.9], [0.0, 0.95, 0.7]];
+        let matches = match_leftovers(&alignment, 0.6, MatchKind::Content, |l, r| scores[l][r]);
+
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.left, m.right))
+                .collect::<Vec<_>>(),
+            vec![(2, 1), (1, 2)]
+        );
+        assert_eq!(
+            apply_matches(alignment, &matches),
+            vec![(Some(0), Some(0)), (Some(1), Some(2)), (Some(2), Some(1))]
+        );
+    }
+}
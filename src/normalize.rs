@@ -0,0 +1,194 @@
+use crate::asm_file::Block;
+use crate::line::OperandKind;
+use crate::Line;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// absolute address printed before a symbol reference: `call 14700 <foo>`
+static RE_ADDRESS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b[0-9a-f]+ <").expect("bug: wrong regex"));
+
+// displacement of rip-relative operands (Intel and AT&T)
+static RE_RIP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"rip[+-]0x[0-9a-f]+|-?0x[0-9a-f]+\(%rip\)").expect("bug: wrong regex")
+});
+
+// compiler-generated local labels, numbered per function or per file
+static RE_LOCAL_LABEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\.(LBB|Ltmp|Lfunc_end|Lfunc_begin|LJTI)[0-9]+(_[0-9]+)?")
+        .expect("bug: wrong regex")
+});
+
+// references that change when the basic blocks of a function move
+static RE_BB_REFERENCE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<self\+0x[0-9a-f]+>|\.LBB_[0-9]+").expect("bug: wrong regex"));
+
+const NOPS: &[&str] = &["int3", "nop", "nopw", "nopl"];
+
+// prefixes objdump shows as ops of their own in long nops
+// (`data16 cs nop WORD PTR [rax+rax*1+0x0]`)
+const NOP_PREFIXES: &[&str] = &["data16", "cs"];
+
+/// Whether a line is alignment padding: a nop in any of its forms (including
+/// `xchg ax,ax`), `int3`, or a line that isn't code.
+fn is_padding(line: &Line) -> bool {
+    match line {
+        Line::Instruction(i) => match i.mnemonic().as_ref() {
+            mnemonic if NOPS.contains(&mnemonic) => true,
+            "xchg" => {
+                let operands = i.operands();
+                operands.len() == 2
+                    && operands[0] == operands[1]
+                    && OperandKind::of(&operands[0]) == OperandKind::Register
+            }
+            mnemonic if NOP_PREFIXES.contains(&mnemonic) => i
+                .content()
+                .split_whitespace()
+                .find(|word| !NOP_PREFIXES.contains(word))
+                .is_some_and(|op| op.starts_with("nop")),
+            _ => false,
+        },
+        Line::Blank | Line::Other(_) => true,
+        _ => false,
+    }
+}
+
+/// The lines without the padding at their end.
+fn without_trailing_padding(lines: &[Line]) -> &[Line] {
+    let end = lines
+        .iter()
+        .rposition(|line| !is_padding(line))
+        .map_or(0, |i| i + 1);
+
+    &lines[..end]
+}
+
+fn normalize_text(text: &str, self_name: &str) -> String {
+    let text = RE_ADDRESS.replace_all(text, "<");
+    let text = RE_RIP.replace_all(&text, "rip");
+    let text = RE_LOCAL_LABEL.replace_all(&text, ".${1}${2}");
+
+    text.replace(&format!("<{}+", self_name), "<self+")
+        .replace(&format!("<{}>", self_name), "<self>")
+}
+
+/// The instructions (including the targets objdump resolves in comments) and
+/// local labels of a block, with everything that depends
+/// on where the block is placed (addresses, references to the block itself,
+/// label numbering, trailing padding) normalized away.
+pub fn normalized_lines(block: &Block) -> Vec<String> {
+    without_trailing_padding(block.lines())
+        .iter()
+        .filter_map(|line| match line {
+            Line::Instruction(i) => Some(normalize_text(i.content(), block.demangled_label())),
+            Line::LocalLabel(label) => Some(normalize_text(label, block.demangled_label())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The instructions of a basic block of a function, normalized like in
+/// `normalized_lines` (including the removal of trailing padding only), with
+/// references to other basic blocks of the function normalized too.
+pub fn normalized_instructions(lines: &[Line], self_name: &str) -> Vec<String> {
+    without_trailing_padding(lines)
+        .iter()
+        .filter_map(|line| match line {
+            Line::Instruction(i) => {
+                let text = normalize_text(i.content(), self_name);
+                Some(RE_BB_REFERENCE.replace_all(&text, "<bb>").into_owned())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn block_hash(block: &Block) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    normalized_lines(block).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::Syntax;
+
+    fn lines(text: &str) -> Vec<Line> {
+        text.lines()
+            .map(|line| Line::from_str(line, Syntax::Intel))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_padding() {
+        let padding = |text: &str| is_padding(&lines(text)[0]);
+
+        assert!(padding("    1000:\t90                   \tnop"));
+        assert!(padding("    1000:\tcc                   \tint3"));
+        assert!(padding("    1000:\t66 90                \txchg   ax,ax"));
+        assert!(padding(
+            "    1000:\t66 2e 0f 1f 84 00 00 \tcs nop WORD PTR [rax+rax*1+0x0]"
+        ));
+        assert!(padding(
+            "    1000:\t66 66 2e 0f 1f 84 00 \tdata16 cs nop WORD PTR [rax+rax*1+0x0]"
+        ));
+        assert!(!padding(
+            "    1000:\t87 07                \txchg   DWORD PTR [rdi],eax"
+        ));
+        assert!(!padding("    1000:\t48 87 d8             \txchg   rax,rbx"));
+        assert!(!padding("    1000:\t0f 0b                \tud2"));
+        assert!(!padding(
+            "    1000:\t2e 74 02             \tcs je   1006 <foo+0x6>"
+        ));
+    }
+
+    #[test]
+    fn test_normalized_instructions() {
+        let normalized = |text: &str| normalized_instructions(&lines(text), "foo");
+
+        // padding inside a basic block is code
+        assert_ne!(
+            normalized(
+                "    1000:\t87 07                \txchg   DWORD PTR [rdi],eax
+    1002:\tc3                   \tret"
+            ),
+            normalized("    1000:\tc3                   \tret")
+        );
+        assert_ne!(
+            normalized(
+                "    1000:\te8 00 00 00 00       \tcall   1005 <panic>
+    1005:\t0f 0b                \tud2"
+            ),
+            normalized("    1000:\te8 00 00 00 00       \tcall   1005 <panic>")
+        );
+        assert_eq!(
+            normalized(
+                "    1000:\tc3                   \tret
+    1001:\t66 90                \txchg   ax,ax
+    1003:\tcc                   \tint3"
+            ),
+            normalized("    1000:\tc3                   \tret")
+        );
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(
+            normalize_text("14826 <a::helper+0x36>", "a::helper"),
+            "<self+0x36>"
+        );
+        assert_eq!(
+            normalize_text("rdi,[rip+0x9a5]        # 14b40 <main>", "foo"),
+            "rdi,[rip]        # <main>"
+        );
+        assert_eq!(
+            normalize_text("lea    0x42889(%rip),%rdi", "foo"),
+            "lea    rip,%rdi"
+        );
+        assert_eq!(normalize_text(".LBB2_4", "foo"), ".LBB_4");
+        assert_eq!(normalize_text(".Ltmp13", "foo"), ".Ltmp");
+    }
+}
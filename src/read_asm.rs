@@ -1,14 +1,178 @@
+use crate::create_asm;
+use crate::gas;
+use crate::line;
+use crate::llvm_ir;
+use crate::wasm;
 use crate::AsmFile;
 use crate::Line;
+use crate::CFG;
+use std::io::Read;
 
-pub fn read_asm_from_memory(output: std::process::Output) -> AsmFile {
+// magic numbers of the object formats objdump is commonly used on
+const BINARY_MAGICS: &[&[u8]] = &[
+    b"\x7fELF",
+    b"MZ",
+    b"!<arch>\n",
+    b"\xfe\xed\xfa\xce",
+    b"\xce\xfa\xed\xfe",
+    b"\xfe\xed\xfa\xcf",
+    b"\xcf\xfa\xed\xfe",
+    b"\xca\xfe\xba\xbe",
+];
+
+/// Whether the contents look like a binary to be disassembled, rather than a
+/// disassembly saved as text.
+fn is_binary(contents: &[u8]) -> bool {
+    let head = &contents[..contents.len().min(4096)];
+
+    BINARY_MAGICS.iter().any(|magic| head.starts_with(magic))
+        || head.contains(&0)
+        || matches!(std::str::from_utf8(head), Err(err) if err.error_len().is_some())
+}
+
+/// Gets the disassembly text for a command line argument, which can be a
+/// binary (disassembled with objdump), a WebAssembly module, a text file with
+/// a saved disassembly, or `-` for a disassembly read from stdin.
+pub fn disassembly_for_arg(arg_filename: &str) -> Option<String> {
+    disassembly_for(arg_filename, std::io::stdin())
+}
+
+fn disassembly_for(arg_filename: &str, mut stdin: impl Read) -> Option<String> {
+    if arg_filename == "-" {
+        let mut text = String::new();
+        stdin
+            .read_to_string(&mut text)
+            .expect("failed to read disassembly from stdin");
+        return Some(text);
+    }
+
+    let contents = std::fs::read(arg_filename)
+        .unwrap_or_else(|_| panic!("failed to read file {}", arg_filename));
+
+    if contents.starts_with(wasm::WASM_MAGIC) {
+        match wasm::disassemble(arg_filename, &contents) {
+            Ok(text) => Some(text),
+            Err(err) => {
+                println!("err:\n\n{}", err);
+                None
+            }
+        }
+    } else if is_binary(&contents) {
+        create_asm::create_asm_for_arg(arg_filename)
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Some(String::from_utf8_lossy(&contents).into_owned())
+    }
+}
+
+pub fn read_asm_for_arg(arg_filename: &str) -> Option<AsmFile> {
+    disassembly_for_arg(arg_filename).map(|text| read_asm_from_str(&text))
+}
+
+/// Guesses the format of a text input: objdump output, assembly source
+/// (e.g. `rustc --emit asm`) or LLVM IR.
+fn detect_format(text: &str) -> &'static str {
+    let is_objdump = text
+        .lines()
+        .take(10)
+        .any(|line| line.contains("file format") || line.starts_with("Disassembly of section"));
+
+    if is_objdump {
+        "objdump"
+    } else if text.starts_with("; ModuleID") || text.lines().any(|line| line.starts_with("define "))
+    {
+        "llvm-ir"
+    } else if text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with(".text") || line.starts_with(".section")
+    }) {
+        "gas"
+    } else {
+        "objdump"
+    }
+}
+
+pub fn read_asm_from_str(text: &str) -> AsmFile {
+    let format = match CFG.input_format.as_str() {
+        "auto" => detect_format(text),
+        format => format,
+    };
+
+    let mut asm = match format {
+        "gas" => gas::read_gas_from_str(text, CFG.strip_debug),
+        "llvm-ir" => llvm_ir::read_llvm_ir_from_str(text, CFG.strip_debug),
+        _ => read_objdump_from_str(text),
+    };
+
+    if CFG.merge_cold {
+        asm.merge_cold_parts();
+    }
+    asm
+}
+
+fn read_objdump_from_str(text: &str) -> AsmFile {
     let mut asm = AsmFile::new();
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let syntax = CFG.syntax();
 
-    for line in stdout.split("\n") {
-        let line = Line::from_str(&line);
+    for line in line::join_continued_bytes(text) {
+        let line = Line::from_str(&line, syntax);
         asm.push(line);
     }
 
     asm
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJDUMP: &str = "
+a.out:     file format elf64-x86-64
+
+
+Disassembly of section .text:
+
+0000000000001000 <main>:
+    1000:\t31 c0                \txor    eax,eax
+    1002:\tc3                   \tret
+";
+
+    #[test]
+    fn test_is_binary() {
+        for magic in BINARY_MAGICS {
+            let mut contents = magic.to_vec();
+            contents.extend_from_slice(b"rest of the file");
+            assert!(is_binary(&contents), "{:?}", magic);
+        }
+        assert!(is_binary(b"\0asm\x01\0\0\0"));
+
+        assert!(!is_binary(OBJDUMP.as_bytes()));
+        assert!(!is_binary("\tcall\t<é>".as_bytes()));
+        assert!(!is_binary(b""));
+        assert!(is_binary(b"text with a\0NUL byte"));
+        assert!(is_binary(b"text that isn't \xff\xfe UTF-8"));
+
+        // a character cut by the end of the checked part is still text
+        let mut contents = vec![b'a'; 4095];
+        contents.extend_from_slice("é".as_bytes());
+        assert!(!is_binary(&contents));
+    }
+
+    #[test]
+    fn test_disassembly_for_stdin() {
+        assert_eq!(
+            disassembly_for("-", OBJDUMP.as_bytes()).as_deref(),
+            Some(OBJDUMP)
+        );
+    }
+
+    #[test]
+    fn test_disassembly_for_text_file() {
+        let path = std::env::temp_dir().join(format!("asmdiff-test-{}.txt", std::process::id()));
+        std::fs::write(&path, OBJDUMP).unwrap();
+
+        let text = disassembly_for(path.to_str().unwrap(), std::io::empty());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text.as_deref(), Some(OBJDUMP));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::asm_file::Block;
+use crate::symbol;
+use crate::CFG;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sizes {
+    pub bytes: usize,
+    pub instructions: usize,
+}
+
+impl Sizes {
+    fn of(block: &Block) -> Self {
+        Self {
+            bytes: block.byte_size(),
+            instructions: block.instruction_count(),
+        }
+    }
+}
+
+/// Sizes of the same function (or group of functions) in both binaries.
+#[derive(Debug)]
+pub struct SizeRow {
+    pub name: String,
+    pub left: Option<Sizes>,
+    pub right: Option<Sizes>,
+}
+
+impl SizeRow {
+    fn bytes_delta(&self) -> i64 {
+        self.right.map_or(0, |s| s.bytes as i64) - self.left.map_or(0, |s| s.bytes as i64)
+    }
+
+    fn instructions_delta(&self) -> i64 {
+        self.right.map_or(0, |s| s.instructions as i64)
+            - self.left.map_or(0, |s| s.instructions as i64)
+    }
+
+    fn percent_delta(&self) -> Option<f64> {
+        match self.left {
+            Some(left) if left.bytes > 0 && self.right.is_some() => {
+                Some(self.bytes_delta() as f64 * 100.0 / left.bytes as f64)
+            }
+            _ => None,
+        }
+    }
+
+    fn status(&self) -> &str {
+        match (self.left, self.right) {
+            (None, Some(_)) => "added",
+            (Some(_), None) => "removed",
+            _ if self.bytes_delta() != 0 || self.instructions_delta() != 0 => "changed",
+            _ => "",
+        }
+    }
+
+    fn passes_threshold(&self, threshold: &str) -> bool {
+        match threshold.strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.parse().expect("invalid --threshold");
+                self.percent_delta().is_none_or(|p| p.abs() >= percent)
+            }
+            None => {
+                let bytes: i64 = threshold.parse().expect("invalid --threshold");
+                self.bytes_delta().abs() >= bytes
+            }
+        }
+    }
+}
+
+pub fn size_rows(
+    blocks1: &[Block],
+    blocks2: &[Block],
+    alignment: &[(Option<usize>, Option<usize>)],
+) -> Vec<SizeRow> {
+    alignment
+        .iter()
+        .filter_map(|(l, r)| {
+            let name = match (l, r) {
+                (_, Some(r)) => blocks2[*r].demangled_label(),
+                (Some(l), None) => blocks1[*l].demangled_label(),
+                (None, None) => return None,
+            };
+
+            Some(SizeRow {
+                name: name.to_owned(),
+                left: l.map(|l| Sizes::of(&blocks1[l])),
+                right: r.map(|r| Sizes::of(&blocks2[r])),
+            })
+        })
+        .collect()
+}
+
+/// Merges the rows of functions that share the first `depth` segments of
+/// their paths.
+pub fn group_rows(rows: Vec<SizeRow>, depth: usize) -> Vec<SizeRow> {
+    let mut groups: HashMap<String, SizeRow> = HashMap::new();
+    let add = |total: Option<Sizes>, sizes: Option<Sizes>| match (total, sizes) {
+        (Some(t), Some(s)) => Some(Sizes {
+            bytes: t.bytes + s.bytes,
+            instructions: t.instructions + s.instructions,
+        }),
+        (t, s) => t.or(s),
+    };
+
+    for row in rows {
+        let name = symbol::group_name(&row.name, depth);
+        let group = groups.entry(name.clone()).or_insert(SizeRow {
+            name,
+            left: None,
+            right: None,
+        });
+
+        group.left = add(group.left, row.left);
+        group.right = add(group.right, row.right);
+    }
+
+    groups.into_values().collect()
+}
+
+/// The number of rows and the deltas in bytes and instructions of each
+/// status, then of all rows.
+fn totals(rows: &[SizeRow]) -> Vec<(&'static str, usize, i64, i64)> {
+    let sum = |selected: &[&SizeRow]| {
+        (
+            selected.len(),
+            selected.iter().map(|row| row.bytes_delta()).sum(),
+            selected.iter().map(|row| row.instructions_delta()).sum(),
+        )
+    };
+
+    let mut totals: Vec<_> = ["added", "removed", "changed"]
+        .into_iter()
+        .map(|category| {
+            let selected: Vec<_> = rows.iter().filter(|row| row.status() == category).collect();
+            let (count, bytes, instructions) = sum(&selected);
+            (category, count, bytes, instructions)
+        })
+        .collect();
+
+    let (count, bytes, instructions) = sum(&rows.iter().collect::<Vec<_>>());
+    totals.push(("total", count, bytes, instructions));
+    totals
+}
+
+fn print_totals(rows: &[SizeRow]) {
+    println!();
+    for (category, count, bytes, instructions) in totals(rows) {
+        println!(
+            "{:>8}: {:>5} {:>+9} bytes {:>+8} instructions",
+            category, count, bytes, instructions
+        );
+    }
+}
+
+fn sort_rows(rows: &mut [SizeRow], sort: &str) {
+    match sort {
+        "name" => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        "size" => rows.sort_by_key(|row| std::cmp::Reverse(row.right.map_or(0, |s| s.bytes))),
+        "percent" => rows.sort_by(|a, b| {
+            let key = |row: &SizeRow| row.percent_delta().map_or(f64::INFINITY, f64::abs);
+            key(b).total_cmp(&key(a))
+        }),
+        _ => rows.sort_by_key(|row| std::cmp::Reverse(row.bytes_delta().abs())),
+    }
+}
+
+/// Prints the sizes of both sides with their deltas, sorted and filtered
+/// according to `--sort` and `--threshold`, followed by totals.
+pub fn print_size_diff(mut rows: Vec<SizeRow>) {
+    sort_rows(&mut rows, &CFG.sort);
+
+    let fmt = |sizes: Option<Sizes>, f: fn(Sizes) -> usize| {
+        sizes.map_or("-".to_owned(), |s| f(s).to_string())
+    };
+
+    println!(
+        "{:>8} {:>8} {:>8} {:>8} {:>6} {:>6} {:>6} {:<8} name",
+        "bytes", "bytes", "delta", "%", "ins", "ins", "delta", "status"
+    );
+    for row in &rows {
+        if row.status().is_empty() && CFG.only_diff
+            || CFG
+                .threshold
+                .as_ref()
+                .is_some_and(|t| !row.passes_threshold(t))
+        {
+            continue;
+        }
+
+        println!(
+            "{:>8} {:>8} {:>+8} {:>8} {:>6} {:>6} {:>+6} {:<8} {}",
+            fmt(row.left, |s| s.bytes),
+            fmt(row.right, |s| s.bytes),
+            row.bytes_delta(),
+            row.percent_delta()
+                .map_or("".to_owned(), |p| format!("{:+.1}%", p)),
+            fmt(row.left, |s| s.instructions),
+            fmt(row.right, |s| s.instructions),
+            row.instructions_delta(),
+            row.status(),
+            CFG.name_policy().apply(&row.name)
+        );
+    }
+
+    print_totals(&rows);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_asm::read_asm_from_str;
+
+    fn row(name: &str, left: Option<usize>, right: Option<usize>) -> SizeRow {
+        let sizes = |bytes| Sizes {
+            bytes,
+            instructions: bytes / 4,
+        };
+
+        SizeRow {
+            name: name.to_owned(),
+            left: left.map(sizes),
+            right: right.map(sizes),
+        }
+    }
+
+    fn rows() -> Vec<SizeRow> {
+        vec![
+            row("a::same", Some(40), Some(40)),
+            row("a::grown", Some(100), Some(120)),
+            row("b::shrunk", Some(16), Some(8)),
+            row("b::added", None, Some(32)),
+            row("c::removed", Some(64), None),
+        ]
+    }
+
+    fn names(rows: &[SizeRow]) -> Vec<&str> {
+        rows.iter().map(|row| row.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_size_rows() {
+        let left = read_asm_from_str(
+            "
+Disassembly of section .text:
+
+0000000000001000 <foo>:
+    1000:\t55                   \tpush   rbp
+    1001:\tc3                   \tret
+
+0000000000001010 <gone>:
+    1010:\tc3                   \tret
+",
+        );
+        let right = read_asm_from_str(
+            "
+Disassembly of section .text:
+
+0000000000002000 <foo>:
+    2000:\t55                   \tpush   rbp
+    2001:\t48 89 e5             \tmov    rbp,rsp
+    2004:\tc3                   \tret
+
+0000000000002010 <new>:
+    2010:\tc3                   \tret
+",
+        );
+        let blocks1 = left.get_section(".text").unwrap().blocks();
+        let blocks2 = right.get_section(".text").unwrap().blocks();
+
+        let rows = size_rows(
+            blocks1,
+            blocks2,
+            &[(Some(0), Some(0)), (Some(1), None), (None, Some(1))],
+        );
+
+        assert_eq!(names(&rows), ["foo", "gone", "new"]);
+        assert_eq!(rows[0].bytes_delta(), 3);
+        assert_eq!(rows[0].instructions_delta(), 1);
+        assert_eq!(rows[0].status(), "changed");
+        assert_eq!(rows[1].status(), "removed");
+        assert_eq!(rows[1].bytes_delta(), -1);
+        assert_eq!(rows[2].status(), "added");
+        assert_eq!(rows[2].percent_delta(), None);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let rows = rows();
+
+        assert!(!rows[0].passes_threshold("1"));
+        assert!(rows[1].passes_threshold("20"));
+        assert!(!rows[1].passes_threshold("21"));
+        assert!(rows[2].passes_threshold("-8"));
+        assert!(rows[1].passes_threshold("20%"));
+        assert!(!rows[1].passes_threshold("21%"));
+        assert!(rows[2].passes_threshold("50%"));
+        // added and removed functions have no percentage and always show
+        assert!(rows[3].passes_threshold("1000%"));
+        assert!(rows[4].passes_threshold("1000%"));
+        assert!(!rows[3].passes_threshold("33"));
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let mut rows = rows();
+
+        sort_rows(&mut rows, "name");
+        assert_eq!(
+            names(&rows),
+            ["a::grown", "a::same", "b::added", "b::shrunk", "c::removed"]
+        );
+
+        sort_rows(&mut rows, "delta");
+        assert_eq!(
+            names(&rows),
+            ["c::removed", "b::added", "a::grown", "b::shrunk", "a::same"]
+        );
+
+        sort_rows(&mut rows, "size");
+        assert_eq!(
+            names(&rows),
+            ["a::grown", "a::same", "b::added", "b::shrunk", "c::removed"]
+        );
+
+        // rows without a percentage first
+        sort_rows(&mut rows, "percent");
+        assert_eq!(
+            names(&rows),
+            ["b::added", "c::removed", "b::shrunk", "a::grown", "a::same"]
+        );
+    }
+
+    #[test]
+    fn test_group_rows() {
+        let mut groups = group_rows(rows(), 1);
+        sort_rows(&mut groups, "name");
+
+        assert_eq!(names(&groups), ["a", "b", "c"]);
+        assert_eq!(groups[0].left.unwrap().bytes, 140);
+        assert_eq!(groups[0].right.unwrap().bytes, 160);
+        // a group with an added function exists on both sides
+        assert_eq!(groups[1].left.unwrap().bytes, 16);
+        assert_eq!(groups[1].right.unwrap().bytes, 40);
+        assert_eq!(groups[1].status(), "changed");
+        assert_eq!(groups[2].status(), "removed");
+    }
+
+    #[test]
+    fn test_totals() {
+        assert_eq!(
+            totals(&rows()),
+            vec![
+                ("added", 1, 32, 8),
+                ("removed", 1, -64, -16),
+                ("changed", 2, 12, 3),
+                ("total", 5, -20, -5),
+            ]
+        );
+    }
+}
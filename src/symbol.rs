@@ -0,0 +1,564 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+
+static RE_HASH_SEGMENT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^h[a-f0-9]{16}$").expect("bug: wrong regex"));
+
+static RE_CLOSURE_INDEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{closure#[0-9]+\}").expect("bug: wrong regex"));
+
+// `.llvm.<hash>` added by (Thin)LTO, and numbered compiler-generated parts
+// of functions
+static RE_LLVM_SUFFIX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\.llvm\.[0-9A-Fa-f@]+").expect("bug: wrong regex"));
+
+static RE_NUMBERED_PART: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\.(cold|part|isra|constprop)(?:\.[0-9]+)+\b").expect("bug: wrong regex")
+});
+
+// outlined cold parts of functions (`foo.cold`, `foo.cold.1`)
+static RE_COLD_PART: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+)\.cold(?:\.[0-9]+)?$").expect("bug: wrong regex"));
+
+static RE_DISAMBIGUATOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[[a-f0-9]+\]").expect("bug: wrong regex"));
+
+/// The name of the function an outlined cold part (`foo.cold.1`) belongs to.
+pub fn cold_parent(name: &str) -> Option<&str> {
+    RE_COLD_PART
+        .captures(name)
+        .map(|caps| caps.get(1).expect("bug: wrong regex").as_str())
+}
+
+/// Splits a demangled name on the `::` separators that are not nested in
+/// generic arguments, qualified paths or other brackets.
+pub fn split_path(name: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut depth = 0_i32;
+    let mut start = 0;
+    let bytes = name.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'<' | b'(' | b'[' | b'{' => depth += 1,
+            b'>' if i > 0 && bytes[i - 1] == b'-' => (),
+            b'>' | b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 && bytes.get(i + 1) == Some(&b':') => {
+                segments.push(&name[start..i]);
+                start = i + 2;
+                i += 1;
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    segments.push(&name[start..]);
+
+    segments
+}
+
+/// The segments of a name without the trailing hash of a legacy Rust symbol.
+fn path_without_hash(name: &str) -> Vec<&str> {
+    let mut segments = split_path(name);
+
+    if segments.len() > 1 && RE_HASH_SEGMENT.is_match(segments[segments.len() - 1]) {
+        segments.pop();
+    }
+    segments
+}
+
+/// Replaces the generic argument lists of every path segment of a name,
+/// keeping qualified paths (`<Vec<T> as Drop>::drop`). Without a
+/// replacement, the `::` of a turbofish goes too.
+fn replace_generics(name: &str, replacement: Option<&str>) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut depth = 0_i32;
+    let mut previous = ' ';
+
+    for c in name.chars() {
+        let after_path = previous.is_alphanumeric() || previous == '_' || previous == ':';
+
+        match c {
+            // a qualified path is not a generic argument list
+            '<' if depth == 0 && !after_path => result.push(c),
+            '<' => {
+                if depth == 0 {
+                    match replacement {
+                        Some(replacement) => result.push_str(replacement),
+                        // turbofish
+                        None if result.ends_with("::") => result.truncate(result.len() - 2),
+                        None => (),
+                    }
+                }
+                depth += 1;
+            }
+            '>' if previous == '-' && depth == 0 => result.push(c),
+            '>' if previous == '-' => (),
+            '>' if depth > 0 => depth -= 1,
+            c if depth == 0 => result.push(c),
+            _ => (),
+        }
+        previous = c;
+    }
+
+    result
+}
+
+/// Removes the generic arguments of every path segment of a name
+/// (`Vec<T>::push<u8>` -> `Vec::push`).
+pub fn strip_generics(name: &str) -> String {
+    replace_generics(name, None)
+}
+
+/// The name of a symbol without its hash and generic arguments, shared by all
+/// the instantiations of a generic item.
+pub fn generic_item(name: &str) -> String {
+    let segments = path_without_hash(name);
+    let name = strip_generics(&segments.join("::"));
+    RE_DISAMBIGUATOR.replace_all(&name, "").into_owned()
+}
+
+/// The path of the item a symbol belongs to, for grouping symbols by crate
+/// and module. Qualified paths (`<Vec<T> as Drop>::drop`) are grouped under
+/// the self type, or under the trait when the self type has no path (e.g.
+/// slices and references to them).
+pub fn item_path(name: &str) -> Vec<String> {
+    let segments = path_without_hash(name);
+
+    let mut path = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let qualified = segment
+            .strip_prefix('<')
+            .and_then(|segment| segment.strip_suffix('>'));
+
+        match qualified {
+            Some(qualified) if i == 0 => {
+                let (self_type, trait_path) = match qualified.split_once(" as ") {
+                    Some((self_type, trait_path)) => (self_type, Some(trait_path)),
+                    None => (qualified, None),
+                };
+                let self_type = self_type
+                    .trim_start_matches('&')
+                    .trim_start_matches("mut ")
+                    .trim_start_matches("*const ")
+                    .trim_start_matches("*mut ")
+                    .trim_start_matches("dyn ");
+
+                if self_type.starts_with(['[', '(']) || !self_type.contains("::") {
+                    match trait_path {
+                        Some(trait_path) => path.extend(item_path(trait_path)),
+                        None => path.push(strip_generics(self_type)),
+                    }
+                } else {
+                    path.extend(item_path(self_type));
+                }
+            }
+            _ => path.push(strip_generics(segment)),
+        }
+    }
+
+    path.iter_mut().for_each(|segment| {
+        *segment = RE_DISAMBIGUATOR.replace_all(segment, "").into_owned();
+    });
+    path
+}
+
+/// The group a symbol belongs to at a given depth of its path (`1` for the
+/// crate, `2` for the top-level module, etc.).
+pub fn group_name(name: &str, depth: usize) -> String {
+    let path = item_path(name);
+
+    if path.len() < 2 {
+        return "(no path)".to_owned();
+    }
+
+    path[..depth.min(path.len() - 1)].join("::")
+}
+
+// items of the standard library shown without their paths by
+// `--abbreviate-paths`
+const WELL_KNOWN_PATHS: &[&str] = &[
+    "boxed::Box",
+    "cell::Cell",
+    "cell::RefCell",
+    "collections::hash::map::HashMap",
+    "collections::hash::set::HashSet",
+    "fmt::Arguments",
+    "fmt::Formatter",
+    "option::Option",
+    "rc::Rc",
+    "result::Result",
+    "string::String",
+    "sync::Arc",
+    "vec::Vec",
+];
+
+static RE_WELL_KNOWN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"\b(?:alloc|core|std)(?:\[[0-9a-f]+\])?::(?:{})\b",
+        WELL_KNOWN_PATHS.join("|")
+    ))
+    .expect("bug: wrong regex")
+});
+
+/// How to shorten symbol names for display. Only the displayed names are
+/// shortened, matching always uses the full names.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NamePolicy {
+    pub elide_generics: bool,
+    pub keep_segments: Option<usize>,
+    pub abbreviate_paths: bool,
+    pub max_width: Option<usize>,
+}
+
+impl NamePolicy {
+    pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut name = Cow::Borrowed(name);
+
+        if self.abbreviate_paths {
+            name = Cow::Owned(abbreviate_paths(&name));
+        }
+        if self.elide_generics {
+            name = Cow::Owned(elide_generics(&name));
+        }
+        if let Some(n) = self.keep_segments {
+            name = Cow::Owned(keep_last_segments(&name, n));
+        }
+        match self.max_width {
+            Some(width) => Cow::Owned(truncate_middle(&name, width)),
+            None => name,
+        }
+    }
+}
+
+/// Shows well-known items of the standard library by their names alone
+/// (`alloc::vec::Vec` becomes `Vec`).
+fn abbreviate_paths(name: &str) -> String {
+    RE_WELL_KNOWN
+        .replace_all(name, |caps: &regex::Captures| {
+            caps[0].rsplit("::").next().unwrap_or_default().to_owned()
+        })
+        .into_owned()
+}
+
+/// Replaces generic argument lists by `<…>`, keeping qualified paths
+/// (`<Vec<…> as Drop>::drop`).
+fn elide_generics(name: &str) -> String {
+    replace_generics(name, Some("<…>"))
+}
+
+/// Keeps the last `n` segments of the path, marking the dropped ones by `…`.
+fn keep_last_segments(name: &str, n: usize) -> String {
+    let mut segments: Vec<String> = Vec::new();
+
+    for segment in split_path(name) {
+        match segments.last_mut() {
+            // a turbofish belongs to the segment before it
+            Some(last) if segment.starts_with('<') => {
+                last.push_str("::");
+                last.push_str(segment);
+            }
+            _ => segments.push(segment.to_owned()),
+        }
+    }
+
+    if segments.len() <= n.max(1) {
+        name.to_owned()
+    } else {
+        format!("…::{}", segments[segments.len() - n.max(1)..].join("::"))
+    }
+}
+
+/// Cuts the middle of names longer than `width` characters, so that both the
+/// crate and the item stay visible. The last segment of the path is kept
+/// whole if it fits.
+fn truncate_middle(name: &str, width: usize) -> String {
+    let count = name.chars().count();
+
+    if count <= width || width < 3 {
+        return name.to_owned();
+    }
+
+    let segments = split_path(name);
+    let item = segments[segments.len() - 1].chars().count();
+    let tail = if segments.len() > 1 && item + 2 <= width / 2 {
+        item + 2
+    } else {
+        width / 2
+    };
+    let head = width - 1 - tail;
+    let mut result: String = name.chars().take(head).collect();
+    result.push('…');
+    result.extend(name.chars().skip(count - tail));
+    result
+}
+
+/// Removes the parts of symbol suffixes that change between builds: LTO
+/// hashes are dropped and numbered parts (`.cold.1`, `.part.0`) lose their
+/// numbers.
+pub fn normalize_suffixes(name: &str) -> Cow<'_, str> {
+    match RE_LLVM_SUFFIX.replace_all(name, "") {
+        Cow::Borrowed(name) => RE_NUMBERED_PART.replace_all(name, ".$1"),
+        Cow::Owned(name) => Cow::Owned(RE_NUMBERED_PART.replace_all(&name, ".$1").into_owned()),
+    }
+}
+
+/// Drops the trailing hash segment of a legacy Rust symbol, if any.
+pub fn strip_hash(name: &str) -> &str {
+    match split_path(name).last() {
+        Some(last) if RE_HASH_SEGMENT.is_match(last) && name.len() > last.len() + 2 => {
+            &name[..name.len() - last.len() - 2]
+        }
+        _ => name,
+    }
+}
+
+/// Cost of a change to a module segment of a path, small enough that a
+/// function moved by a few modules still clears the default
+/// `--min-similarity`.
+const MODULE_COST: f64 = 0.05;
+
+/// Cost of a change to the generic arguments or closure numbering of a
+/// segment.
+const GENERICS_COST: f64 = 0.05;
+
+/// Which segments of a path are part of the item rather than the modules
+/// it is in: the last one, the functions its closures and shims belong to,
+/// and the type they are associated with.
+fn item_segments(path: &[&str]) -> Vec<bool> {
+    let mut item = vec![false; path.len()];
+    let mut i = path.len() - 1;
+
+    item[i] = true;
+    while i > 0 && path[i].starts_with('{') {
+        i -= 1;
+        item[i] = true;
+    }
+    if i > 0 && path[i - 1].starts_with(|c: char| c.is_uppercase() || c == '<') {
+        item[i - 1] = true;
+    }
+
+    item
+}
+
+/// Cost of replacing a path segment by another: nothing if they are equal,
+/// little if they only differ in generic arguments or closure numbering or
+/// are both modules, and everything otherwise.
+fn segment_cost((a, a_item): (&str, bool), (b, b_item): (&str, bool)) -> f64 {
+    let strip = |segment: &str| {
+        RE_CLOSURE_INDEX
+            .replace_all(&strip_generics(segment), "{closure}")
+            .into_owned()
+    };
+
+    if a == b {
+        0.0
+    } else if strip(a) == strip(b) {
+        GENERICS_COST
+    } else if !a_item && !b_item {
+        MODULE_COST
+    } else {
+        1.0
+    }
+}
+
+/// How likely two symbols are the same item after a change of module path,
+/// generic arguments or closure numbering, between 0 and 1: 1 minus the edit
+/// distance between the paths, where changes to modules and generic
+/// arguments cost little and changes to the item (the function, the type it
+/// belongs to, the function of a closure) cost everything.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let path1 = path_without_hash(a);
+    let path2 = path_without_hash(b);
+    let segments1: Vec<(&str, bool)> = path1.iter().copied().zip(item_segments(&path1)).collect();
+    let segments2: Vec<(&str, bool)> = path2.iter().copied().zip(item_segments(&path2)).collect();
+    let indel = |(_, item): (&str, bool)| if item { 1.0 } else { MODULE_COST };
+
+    // edit distance over segments
+    let mut row: Vec<f64> = vec![0.0];
+    for segment in &segments2 {
+        row.push(row[row.len() - 1] + indel(*segment));
+    }
+    for segment1 in &segments1 {
+        let mut diagonal = row[0];
+        row[0] += indel(*segment1);
+
+        for j in 1..=segments2.len() {
+            let substitution = diagonal + segment_cost(*segment1, segments2[j - 1]);
+            diagonal = row[j];
+            row[j] = substitution
+                .min(row[j] + indel(*segment1))
+                .min(row[j - 1] + indel(segments2[j - 1]));
+        }
+    }
+
+    (1.0 - row[segments2.len()]).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("a::parse"), vec!["a", "parse"]);
+        assert_eq!(
+            split_path("<alloc::vec::Vec<T> as core::ops::Drop>::drop"),
+            vec!["<alloc::vec::Vec<T> as core::ops::Drop>", "drop"]
+        );
+        assert_eq!(
+            split_path("core::ptr::drop_in_place<fn() -> u8>::h0123456789abcdef"),
+            vec![
+                "core",
+                "ptr",
+                "drop_in_place<fn() -> u8>",
+                "h0123456789abcdef"
+            ]
+        );
+        assert_eq!(split_path("main"), vec!["main"]);
+    }
+
+    #[test]
+    fn test_strip_generics() {
+        assert_eq!(
+            strip_generics("alloc::vec::Vec<T,A>::push"),
+            "alloc::vec::Vec::push"
+        );
+        assert_eq!(
+            strip_generics("<alloc::vec::Vec<T> as core::ops::Drop>::drop"),
+            "<alloc::vec::Vec as core::ops::Drop>::drop"
+        );
+        assert_eq!(
+            strip_generics("core::ptr::drop_in_place<fn() -> u8>"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            strip_generics("core::ptr::drop_in_place::<alloc::vec::Vec<u8>>"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            strip_generics("<<T as a::Foo>::Bar<u8> as a::Baz>::f"),
+            "<<T as a::Foo>::Bar as a::Baz>::f"
+        );
+    }
+
+    #[test]
+    fn test_generic_item() {
+        assert_eq!(
+            generic_item("alloc::raw_vec::RawVec<T,A>::grow_one::h1212d1987e1f53db"),
+            "alloc::raw_vec::RawVec::grow_one"
+        );
+        assert_eq!(
+            generic_item("core::ptr::drop_in_place<std::env::Args>"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            generic_item("<core[c1f1a4ba060b9bfa]::cell::once::OnceCell<u8>>::try_init::<<core[c1f1a4ba060b9bfa]::cell::once::OnceCell<u8>>::get_or_init::{closure#0}, !>"),
+            "<core::cell::once::OnceCell>::try_init"
+        );
+    }
+
+    #[test]
+    fn test_name_policy() {
+        let name = "<alloc::vec::Vec<alloc::string::String> as core::ops::drop::Drop>::drop";
+        let policy = |f: fn(&mut NamePolicy)| {
+            let mut policy = NamePolicy::default();
+            f(&mut policy);
+            policy.apply(name).into_owned()
+        };
+
+        assert_eq!(policy(|_| ()), name);
+        assert_eq!(
+            policy(|p| p.abbreviate_paths = true),
+            "<Vec<String> as core::ops::drop::Drop>::drop"
+        );
+        assert_eq!(
+            policy(|p| p.elide_generics = true),
+            "<alloc::vec::Vec<…> as core::ops::drop::Drop>::drop"
+        );
+        assert_eq!(policy(|p| p.keep_segments = Some(1)), "…::drop");
+        assert_eq!(policy(|p| p.max_width = Some(21)), "<alloc::vec::V…::drop");
+        assert_eq!(
+            NamePolicy {
+                max_width: Some(15),
+                ..Default::default()
+            }
+            .apply("a::an_unusually_long_item"),
+            "a::an_u…ng_item"
+        );
+        assert_eq!(
+            NamePolicy {
+                elide_generics: true,
+                keep_segments: Some(2),
+                ..Default::default()
+            }
+            .apply("core[abc]::iter::Map<I, F>::fold::<u8, fn(u8) -> u8>"),
+            "…::Map<…>::fold::<…>"
+        );
+    }
+
+    #[test]
+    fn test_normalize_suffixes() {
+        assert_eq!(normalize_suffixes("foo.llvm.12345678901234"), "foo");
+        assert_eq!(
+            normalize_suffixes("a::foo::h0123456789abcdef.cold.1"),
+            "a::foo::h0123456789abcdef.cold"
+        );
+        assert_eq!(normalize_suffixes("foo.cold"), "foo.cold");
+        assert_eq!(normalize_suffixes("foo.part.0.llvm.9D1C9369"), "foo.part");
+        assert_eq!(
+            normalize_suffixes("call <foo.constprop.0+0x10>"),
+            "call <foo.constprop+0x10>"
+        );
+        assert_eq!(normalize_suffixes("a::cold_path"), "a::cold_path");
+    }
+
+    #[test]
+    fn test_strip_hash() {
+        assert_eq!(strip_hash("a::parse::h93caca7f32fbdaee"), "a::parse");
+        assert_eq!(strip_hash("a::parse"), "a::parse");
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        assert_eq!(name_similarity("a::parse", "a::parse"), 1.0);
+        assert!(close(name_similarity("a::parse", "b::parse"), 0.95));
+        assert!(close(name_similarity("a::parse", "b::c::d::parse"), 0.85));
+        assert!(close(name_similarity("a::Foo::new", "b::Foo::new"), 0.95));
+        assert!(name_similarity("a::foo<u32>", "a::foo<u64>") > 0.9);
+        assert!(name_similarity("a::parse::{closure#0}", "a::parse::{closure#2}") > 0.9);
+        assert!(
+            name_similarity(
+                "a::parse::h0123456789abcdef",
+                "a::inner::parse::hfedcba9876543210"
+            ) > name_similarity("a::parse", "b::c::parse")
+        );
+
+        // different items, whatever their paths
+        assert_eq!(name_similarity("a::parse", "a::lex"), 0.0);
+        assert!(name_similarity("a::Foo::new", "b::Bar::new") < 0.8);
+        assert!(name_similarity("a::Foo::new", "a::Bar::new") < 0.8);
+        assert!(name_similarity("a::parse::{closure#0}", "z::lex::{closure#0}") < 0.8);
+        assert!(name_similarity("a::parse::{closure#0}", "a::lex::{closure#0}") < 0.8);
+    }
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(group_name("a::parse::h0123456789abcdef", 1), "a");
+        assert_eq!(
+            group_name("<alloc::vec::Vec<T> as core::ops::Drop>::drop", 2),
+            "alloc::vec"
+        );
+        assert_eq!(group_name("<[T] as core::fmt::Debug>::fmt", 2), "core::fmt");
+        assert_eq!(
+            group_name("gimli[9d272e8433f81454]::read::line::parse_file_v5::<R>", 3),
+            "gimli::read::line"
+        );
+        assert_eq!(group_name("core::ptr::drop_in_place<u8>", 5), "core::ptr");
+        assert_eq!(group_name("_start", 1), "(no path)");
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use wasmparser::{
+    BlockType, BrTable, Catch, HeapType, Ieee32, Ieee64, MemArg, Name, NameSectionReader, Operator,
+    Parser, Payload, RefType, TryTable, TypeRef, ValType, V128,
+};
+
+pub const WASM_MAGIC: &[u8] = b"\0asm";
+
+// ops whose text name starts with a namespace, as in `i32.add` or `local.get`
+const NAMESPACES: &[&str] = &[
+    "i32", "i64", "f32", "f64", "v128", "i8x16", "i16x8", "i32x4", "i64x2", "f32x4", "f64x2",
+    "local", "global", "memory", "table", "ref", "data", "elem", "atomic", "struct", "array",
+    "i31", "any", "extern",
+];
+
+macro_rules! define_op_functions {
+    ($( @$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        fn visit_name(op: &Operator) -> &'static str {
+            match op {
+                $( Operator::$op { .. } => stringify!($visit), )*
+            }
+        }
+
+        /// The immediates of an op, in the order of its fields.
+        fn immediates(op: &Operator) -> Vec<String> {
+            match op {
+                $(
+                    Operator::$op $({ $($arg),* })? => {
+                        #[allow(unused_mut)]
+                        let mut immediates = Vec::new();
+                        $( $( immediates.extend($arg.text()); )* )?
+                        immediates
+                    }
+                )*
+            }
+        }
+    }
+}
+
+wasmparser::for_each_operator!(define_op_functions);
+
+/// The name of an op in the text format, derived from the name of its
+/// visitor (`visit_i32_add` -> `i32.add`, `visit_i32_atomic_rmw8_add_u` ->
+/// `i32.atomic.rmw8.add_u`).
+fn mnemonic(op: &Operator) -> String {
+    let name = visit_name(op).trim_start_matches("visit_");
+
+    let name = match NAMESPACES.iter().find(|namespace| {
+        name.strip_prefix(*namespace)
+            .is_some_and(|rest| rest.starts_with('_'))
+    }) {
+        Some(namespace) => format!("{}.{}", namespace, &name[namespace.len() + 1..]),
+        None => return name.to_owned(),
+    };
+
+    match name.split_once("atomic_") {
+        Some((before, after)) => {
+            let after = match after.split_once('_') {
+                Some((rmw, op)) if rmw.starts_with("rmw") => format!("{}.{}", rmw, op),
+                _ => after.to_owned(),
+            };
+            format!("{}atomic.{}", before, after)
+        }
+        None => name,
+    }
+}
+
+/// An immediate of an op.
+trait Immediate {
+    /// The text of the immediate, if it shows in the text format.
+    fn text(&self) -> Option<String>;
+}
+
+macro_rules! impl_immediate_with_display {
+    ($($ty:ty),*) => {
+        $(
+            impl Immediate for $ty {
+                fn text(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+            }
+        )*
+    }
+}
+
+impl_immediate_with_display!(u8, u32, i32, i64, ValType, RefType);
+
+impl Immediate for Ieee32 {
+    fn text(&self) -> Option<String> {
+        Some(f32::from_bits(self.bits()).to_string())
+    }
+}
+
+impl Immediate for Ieee64 {
+    fn text(&self) -> Option<String> {
+        Some(f64::from_bits(self.bits()).to_string())
+    }
+}
+
+impl Immediate for V128 {
+    fn text(&self) -> Option<String> {
+        Some(format!("0x{:032x}", self.i128()))
+    }
+}
+
+impl Immediate for [u8; 16] {
+    fn text(&self) -> Option<String> {
+        Some(
+            self.iter()
+                .map(|lane| lane.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+}
+
+impl Immediate for MemArg {
+    fn text(&self) -> Option<String> {
+        // the alignment is read as an exponent, which can be anything in an
+        // invalid module
+        let align = match 1_u64.checked_shl(self.align.into()) {
+            Some(align) => format!("align={}", align),
+            None => format!("align=2**{}", self.align),
+        };
+        let memory = (self.memory != 0).then(|| self.memory.to_string());
+        let offset = (self.offset != 0).then(|| format!("offset={}", self.offset));
+
+        Some(
+            [memory, offset, Some(align)]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+}
+
+impl Immediate for BlockType {
+    fn text(&self) -> Option<String> {
+        match self {
+            BlockType::Empty => None,
+            BlockType::Type(ty) => Some(format!("(result {})", ty)),
+            BlockType::FuncType(index) => Some(format!("(type {})", index)),
+        }
+    }
+}
+
+impl Immediate for HeapType {
+    fn text(&self) -> Option<String> {
+        Some(match self {
+            HeapType::Concrete(index) => index.to_string(),
+            HeapType::Func => "func".to_owned(),
+            HeapType::Extern => "extern".to_owned(),
+            HeapType::Any => "any".to_owned(),
+            HeapType::None => "none".to_owned(),
+            HeapType::NoExtern => "noextern".to_owned(),
+            HeapType::NoFunc => "nofunc".to_owned(),
+            HeapType::Eq => "eq".to_owned(),
+            HeapType::Struct => "struct".to_owned(),
+            HeapType::Array => "array".to_owned(),
+            HeapType::I31 => "i31".to_owned(),
+            HeapType::Exn => "exn".to_owned(),
+        })
+    }
+}
+
+impl Immediate for BrTable<'_> {
+    fn text(&self) -> Option<String> {
+        let targets: Vec<String> = self
+            .targets()
+            .map(|target| target.map_or_else(|_| "?".to_owned(), |t| t.to_string()))
+            .chain([self.default().to_string()])
+            .collect();
+        Some(targets.join(" "))
+    }
+}
+
+impl Immediate for TryTable {
+    fn text(&self) -> Option<String> {
+        let catches = self.catches.iter().map(|catch| match catch {
+            Catch::One { tag, label } => format!("(catch {} {})", tag, label),
+            Catch::OneRef { tag, label } => format!("(catch_ref {} {})", tag, label),
+            Catch::All { label } => format!("(catch_all {})", label),
+            Catch::AllRef { label } => format!("(catch_all_ref {})", label),
+        });
+
+        Some(
+            self.ty
+                .text()
+                .into_iter()
+                .chain(catches)
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+}
+
+fn operands(op: &Operator, names: &HashMap<u32, String>) -> String {
+    match op {
+        Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+            format!("<{}>", function_name(*function_index, names))
+        }
+        _ => immediates(op).join(" "),
+    }
+}
+
+fn function_name(index: u32, names: &HashMap<u32, String>) -> String {
+    names
+        .get(&index)
+        .cloned()
+        .unwrap_or_else(|| format!("func[{}]", index))
+}
+
+/// Disassembles the code section of a WebAssembly module into text in the
+/// format of objdump's output, so that it goes through the same parsing as
+/// native disassemblies. Functions are named from the name section.
+pub fn disassemble(filename: &str, bytes: &[u8]) -> Result<String, wasmparser::BinaryReaderError> {
+    let mut names = HashMap::new();
+    let mut imported_functions = 0;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::CustomSection(reader) if reader.name() == "name" => {
+                for name in NameSectionReader::new(reader.data(), reader.data_offset()) {
+                    if let Name::Function(map) = name? {
+                        for naming in map {
+                            let naming = naming?;
+                            names.insert(naming.index, naming.name.to_owned());
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut text = format!(
+        "\n{}:     file format wasm\n\n\nDisassembly of section code:\n",
+        filename
+    );
+    let mut index = imported_functions;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::CodeSectionEntry(body) = payload? {
+            let name = function_name(index, &names);
+            let _ = write!(text, "\n{:016x} <{}>:\n", body.range().start, name);
+
+            let mut ops = Vec::new();
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                ops.push(reader.read_with_offset()?);
+            }
+
+            for (i, (op, offset)) in ops.iter().enumerate() {
+                let end = ops.get(i + 1).map_or(body.range().end, |(_, next)| *next);
+                let encoding = bytes[*offset..end]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let line = format!("{:<6} {}", mnemonic(op), operands(op, &names));
+                let _ = writeln!(
+                    text,
+                    " {:>8x}:\t{:<20}\t{}",
+                    offset,
+                    encoding,
+                    line.trim_end()
+                );
+            }
+
+            index += 1;
+        }
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memarg(align: u8, offset: u64) -> MemArg {
+        MemArg {
+            align,
+            max_align: 2,
+            offset,
+            memory: 0,
+        }
+    }
+
+    #[test]
+    fn test_mnemonic() {
+        assert_eq!(mnemonic(&Operator::I32Add), "i32.add");
+        assert_eq!(
+            mnemonic(&Operator::LocalGet { local_index: 0 }),
+            "local.get"
+        );
+        assert_eq!(mnemonic(&Operator::BrIf { relative_depth: 0 }), "br_if");
+        assert_eq!(mnemonic(&Operator::I64ExtendI32S), "i64.extend_i32_s");
+        assert_eq!(
+            mnemonic(&Operator::I32AtomicRmwAdd {
+                memarg: memarg(2, 0)
+            }),
+            "i32.atomic.rmw.add"
+        );
+        assert_eq!(
+            mnemonic(&Operator::I64AtomicRmw8AddU {
+                memarg: memarg(0, 0)
+            }),
+            "i64.atomic.rmw8.add_u"
+        );
+        assert_eq!(
+            mnemonic(&Operator::I32AtomicRmwCmpxchg {
+                memarg: memarg(2, 0)
+            }),
+            "i32.atomic.rmw.cmpxchg"
+        );
+        assert_eq!(
+            mnemonic(&Operator::I32AtomicLoad8U {
+                memarg: memarg(0, 0)
+            }),
+            "i32.atomic.load8_u"
+        );
+        assert_eq!(
+            mnemonic(&Operator::MemoryAtomicNotify {
+                memarg: memarg(2, 0)
+            }),
+            "memory.atomic.notify"
+        );
+        assert_eq!(mnemonic(&Operator::AtomicFence), "atomic.fence");
+    }
+
+    #[test]
+    fn test_operands() {
+        let names = HashMap::from([(3, "a::parse".to_owned())]);
+
+        assert_eq!(operands(&Operator::I32Add, &names), "");
+        assert_eq!(operands(&Operator::I32Const { value: -5 }, &names), "-5");
+        assert_eq!(
+            operands(&Operator::Call { function_index: 3 }, &names),
+            "<a::parse>"
+        );
+        assert_eq!(
+            operands(&Operator::Call { function_index: 4 }, &names),
+            "<func[4]>"
+        );
+        assert_eq!(
+            operands(
+                &Operator::Block {
+                    blockty: BlockType::Empty
+                },
+                &names
+            ),
+            ""
+        );
+        assert_eq!(
+            operands(
+                &Operator::Block {
+                    blockty: BlockType::Type(ValType::I32)
+                },
+                &names
+            ),
+            "(result i32)"
+        );
+        assert_eq!(
+            operands(
+                &Operator::RefNull {
+                    hty: HeapType::Func
+                },
+                &names
+            ),
+            "func"
+        );
+    }
+
+    #[test]
+    fn test_memarg() {
+        let names = HashMap::new();
+
+        assert_eq!(
+            operands(
+                &Operator::I32Load {
+                    memarg: memarg(2, 0)
+                },
+                &names
+            ),
+            "align=4"
+        );
+        assert_eq!(
+            operands(
+                &Operator::I64Store {
+                    memarg: memarg(3, 16)
+                },
+                &names
+            ),
+            "offset=16 align=8"
+        );
+        assert_eq!(
+            operands(
+                &Operator::I32Load {
+                    memarg: MemArg {
+                        memory: 1,
+                        ..memarg(2, 8)
+                    }
+                },
+                &names
+            ),
+            "1 offset=8 align=4"
+        );
+        // an alignment exponent out of range must not overflow the shift
+        assert_eq!(
+            operands(
+                &Operator::I32Load {
+                    memarg: memarg(200, 0)
+                },
+                &names
+            ),
+            "align=2**200"
+        );
+    }
+}